@@ -0,0 +1,800 @@
+//! Brute forces the light puzzles of Ldtk levels. Each level's terrain, crystals and buttons are
+//! loaded into a rapier world, and beams of every color are traced from every floor tile the
+//! player can reach, at evenly spaced angles. Reports which buttons can be lit, and the shortest
+//! sequence of shots that lets the player reach an exit they couldn't reach at the start.
+//!
+//! This is an approximation of the game, meant to catch puzzles that became impossible (or
+//! trivial) after a level edit:
+//!
+//! - Movement is a tile based jump model, and ignores dashing, strands and movable objects
+//! - The player only shoots while standing, and movable objects don't block or reflect light
+//! - Beams are traced once when shot, and aren't traced again when the crystals they pass through
+//!   are toggled
+//! - Crystals linked across levels with `world_crystal_ids` are solved as if they were local
+//!
+//! Usage: `cargo run --bin lightborne-solve -- assets/levels/lightborne.ldtk [level]...`
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    f32::consts::TAU,
+    fmt, mem,
+    process::ExitCode,
+};
+
+use bevy::math::{IVec2, Vec2};
+use bevy_ecs_ldtk::ldtk::{LdtkJson, Level};
+use bevy_rapier2d::{
+    prelude::CollisionGroups,
+    rapier::prelude::{
+        point, vector, ColliderBuilder, ColliderHandle, ColliderSet, InteractionGroups,
+        QueryFilter, QueryPipeline, Ray, RigidBodySet,
+    },
+};
+use enum_map::Enum;
+use lightborne::{
+    level::ldtk_data::{button_crystal_key, entity_center, ldtk_crystals, CrystalKey},
+    light::{
+        segments::{trace_light_ray, LightHit},
+        LightColor,
+    },
+    player::movement::{
+        next_vertical_velocity, VerticalInput, JUMP_BOOST_TICKS, PLAYER_HALF_HEIGHT,
+    },
+    shared::GroupLabel,
+};
+
+/// The number of evenly spaced angles each beam is shot at from every floor tile.
+const ANGLE_SAMPLES: usize = 720;
+/// How many tiles the player can jump across.
+const JUMP_DISTANCE: i32 = 5;
+/// How many tiles the player is tall.
+const PLAYER_TILES: i32 = 3;
+/// The most combinations of crystals and used colors searched per level before giving up.
+const MAX_STATES: usize = 4096;
+
+/// What a tile does to the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Empty,
+    Solid,
+    /// Semi-solid platforms can be jumped through and stood on
+    Platform,
+    Spikes,
+}
+
+impl Tile {
+    fn passable(self) -> bool {
+        matches!(self, Tile::Empty | Tile::Platform)
+    }
+
+    fn standable(self) -> bool {
+        matches!(self, Tile::Solid | Tile::Platform)
+    }
+}
+
+/// Where the player leaves a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Exit {
+    Left(i32),
+    Right(i32),
+    Top(i32),
+    Bottom(i32),
+}
+
+impl Exit {
+    /// Whether this exit is part of the same opening as `other`.
+    fn is_near(&self, other: &Exit) -> bool {
+        match (self, other) {
+            (Exit::Left(a), Exit::Left(b))
+            | (Exit::Right(a), Exit::Right(b))
+            | (Exit::Top(a), Exit::Top(b))
+            | (Exit::Bottom(a), Exit::Bottom(b)) => (a - b).abs() <= PLAYER_TILES,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exit::Left(y) => write!(f, "left edge at row {y}"),
+            Exit::Right(y) => write!(f, "right edge at row {y}"),
+            Exit::Top(x) => write!(f, "top edge at column {x}"),
+            Exit::Bottom(x) => write!(f, "bottom edge at column {x}"),
+        }
+    }
+}
+
+struct PuzzleCrystal {
+    color: LightColor,
+    init_active: bool,
+    /// Index into [`Puzzle::keys`]
+    key: usize,
+}
+
+struct PuzzleButton {
+    grid: IVec2,
+    /// The center of the button, in the solver's world coordinates
+    center: Vec2,
+    /// Index into [`Puzzle::keys`]
+    key: usize,
+}
+
+/// A level's terrain, crystals and buttons. Tiles use Ldtk grid coordinates, with y pointing
+/// down, while the rapier world uses pixels relative to the top left of the level with y pointing
+/// up, as in the game, so that angles match the ones the player aims at.
+struct Puzzle {
+    grid_size: f32,
+    /// How many tiles the player can jump up, see [`jump_height`]
+    jump_height: i32,
+    size: IVec2,
+    terrain: Vec<i32>,
+    crystals: HashMap<IVec2, PuzzleCrystal>,
+    buttons: Vec<PuzzleButton>,
+    keys: Vec<CrystalKey>,
+    start: IVec2,
+}
+
+/// The crystals toggled by button presses, as a bit for each of the [`Puzzle::keys`].
+type Toggled = u64;
+
+impl Puzzle {
+    fn tile(&self, toggled: Toggled, cell: IVec2) -> Tile {
+        if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.size).any() {
+            return Tile::Empty;
+        }
+        if let Some(crystal) = self.crystals.get(&cell) {
+            return if self.is_active(crystal, toggled) {
+                Tile::Solid
+            } else {
+                Tile::Empty
+            };
+        }
+        match self.terrain[(cell.y * self.size.x + cell.x) as usize] {
+            1 => Tile::Solid,
+            2 => Tile::Spikes,
+            15 => Tile::Platform,
+            _ => Tile::Empty,
+        }
+    }
+
+    fn is_active(&self, crystal: &PuzzleCrystal, toggled: Toggled) -> bool {
+        crystal.init_active != (toggled & (1 << crystal.key) != 0)
+    }
+
+    fn passable(&self, toggled: Toggled, cells: impl IntoIterator<Item = IVec2>) -> bool {
+        cells
+            .into_iter()
+            .all(|cell| self.tile(toggled, cell).passable())
+    }
+
+    /// Whether the player can stand with their feet in `cell`.
+    fn is_spot(&self, toggled: Toggled, cell: IVec2) -> bool {
+        cell.y >= 0
+            && cell.y < self.size.y
+            && self.passable(toggled, body(cell))
+            && self.tile(toggled, cell + IVec2::Y).standable()
+    }
+
+    /// Whether the player can get from standing at `from` to standing at `to`. Rising jumps go
+    /// straight up and then across, and falls go across and then straight down.
+    fn can_move(&self, toggled: Toggled, from: IVec2, to: IVec2) -> bool {
+        let (turn, rise) = if to.y < from.y {
+            (IVec2::new(from.x, to.y), true)
+        } else {
+            (IVec2::new(to.x, from.y), false)
+        };
+        let column = |a: IVec2, b: IVec2| (a.y.min(b.y)..=a.y.max(b.y)).map(move |y| (a.x, y));
+        let row = |a: IVec2, b: IVec2| (a.x.min(b.x)..=a.x.max(b.x)).map(move |x| (x, a.y));
+        let (first, second): (Vec<_>, Vec<_>) = if rise {
+            (column(from, turn).collect(), row(turn, to).collect())
+        } else {
+            (row(from, turn).collect(), column(turn, to).collect())
+        };
+        first
+            .into_iter()
+            .chain(second)
+            .all(|(x, y)| self.passable(toggled, body(IVec2::new(x, y))))
+    }
+
+    /// Every spot the player can stand at, starting from `start`, and the exits they can reach.
+    fn reachable(&self, toggled: Toggled) -> (Vec<IVec2>, HashSet<Exit>) {
+        let Some(start) = self.land(toggled, self.start) else {
+            return (Vec::new(), HashSet::new());
+        };
+        let mut spots = vec![start];
+        let mut seen = HashSet::from([start]);
+        let mut exits = HashSet::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(spot) = queue.pop_front() {
+            self.find_exits(toggled, spot, &mut exits);
+            for dx in -JUMP_DISTANCE..=JUMP_DISTANCE {
+                for y in spot.y - self.jump_height..self.size.y {
+                    let to = IVec2::new(spot.x + dx, y);
+                    // the player drifts further the further they fall
+                    let reach = JUMP_DISTANCE + (y - spot.y).max(0) / 2;
+                    if dx.abs() > reach
+                        || seen.contains(&to)
+                        || !self.is_spot(toggled, to)
+                        || !self.can_move(toggled, spot, to)
+                    {
+                        continue;
+                    }
+                    seen.insert(to);
+                    spots.push(to);
+                    queue.push_back(to);
+                }
+            }
+        }
+        (spots, exits)
+    }
+
+    /// The spot the player lands on after falling from `cell`.
+    fn land(&self, toggled: Toggled, cell: IVec2) -> Option<IVec2> {
+        (cell.y.max(0)..self.size.y)
+            .map(|y| IVec2::new(cell.x, y))
+            .take_while(|&cell| self.tile(toggled, cell).passable())
+            .find(|&cell| self.is_spot(toggled, cell))
+    }
+
+    fn find_exits(&self, toggled: Toggled, spot: IVec2, exits: &mut HashSet<Exit>) {
+        for (edge, exit) in [(-1, Exit::Left(spot.y)), (self.size.x, Exit::Right(spot.y))] {
+            let beside = IVec2::new(edge, spot.y);
+            if (spot.x - edge).abs() <= JUMP_DISTANCE && self.can_move(toggled, spot, beside) {
+                exits.insert(exit);
+            }
+        }
+        let head = spot.y - PLAYER_TILES + 1;
+        if head - self.jump_height < 0
+            && self.passable(toggled, (0..head).map(|y| IVec2::new(spot.x, y)))
+        {
+            exits.insert(Exit::Top(spot.x));
+        }
+        for x in [spot.x - 1, spot.x + 1] {
+            if self.can_move(toggled, spot, IVec2::new(x, spot.y))
+                && self.passable(toggled, (spot.y..self.size.y).map(|y| IVec2::new(x, y)))
+            {
+                exits.insert(Exit::Bottom(x));
+            }
+        }
+    }
+
+    /// The center of the player when standing at `spot`, which is where beams are shot from.
+    fn shot_origin(&self, spot: IVec2) -> Vec2 {
+        let feet = Vec2::new(spot.x as f32 + 0.5, -(spot.y as f32 + 1.0)) * self.grid_size;
+        feet + Vec2::Y * PLAYER_HALF_HEIGHT
+    }
+
+    /// The center of a tile, in the solver's world coordinates.
+    fn tile_center(&self, cell: IVec2) -> Vec2 {
+        Vec2::new(cell.x as f32 + 0.5, -(cell.y as f32 + 0.5)) * self.grid_size
+    }
+}
+
+/// How many whole tiles of `grid_size` pixels the player can jump up, found by stepping the game's
+/// vertical movement through a jump with jump held the whole way.
+fn jump_height(grid_size: f32) -> i32 {
+    let mut velocity_y = 0.;
+    let mut height = 0.;
+    let mut tick = 0;
+    loop {
+        velocity_y = next_vertical_velocity(
+            velocity_y,
+            VerticalInput {
+                jump_boost: tick < JUMP_BOOST_TICKS,
+                jump_held: true,
+                fast_fall: false,
+            },
+        );
+        if velocity_y <= 0. {
+            return (height / grid_size).floor() as i32;
+        }
+        height += velocity_y;
+        tick += 1;
+    }
+}
+
+/// The cells the player's body takes up when standing in `cell`.
+fn body(cell: IVec2) -> impl Iterator<Item = IVec2> {
+    (0..PLAYER_TILES).map(move |i| cell - IVec2::Y * i)
+}
+
+/// What a collider in a [`PuzzleWorld`] belongs to.
+#[derive(Clone, Copy)]
+enum Target {
+    Terrain,
+    Button(usize),
+    WhiteBeam,
+}
+
+/// The rapier world of a [`Puzzle`] with some crystals toggled.
+struct PuzzleWorld {
+    bodies: RigidBodySet,
+    colliders: ColliderSet,
+    query_pipeline: QueryPipeline,
+    targets: HashMap<ColliderHandle, Target>,
+}
+
+impl PuzzleWorld {
+    fn new(puzzle: &Puzzle, toggled: Toggled, white_beams: &[(Vec2, Vec2)]) -> Self {
+        let mut world = PuzzleWorld {
+            bodies: RigidBodySet::new(),
+            colliders: ColliderSet::new(),
+            query_pipeline: QueryPipeline::new(),
+            targets: HashMap::new(),
+        };
+        let half = puzzle.grid_size / 2.0;
+        let terrain = CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL);
+
+        for y in 0..puzzle.size.y {
+            // merge runs of walls so that beams don't catch on the seams between tiles
+            let mut run_start = None;
+            for x in 0..=puzzle.size.x {
+                let is_wall = x < puzzle.size.x
+                    && !puzzle.crystals.contains_key(&IVec2::new(x, y))
+                    && puzzle.tile(toggled, IVec2::new(x, y)) == Tile::Solid;
+                match (is_wall, run_start) {
+                    (true, None) => run_start = Some(x),
+                    (false, Some(start)) => {
+                        let len = (x - start) as f32;
+                        let center = (puzzle.tile_center(IVec2::new(start, y))
+                            + puzzle.tile_center(IVec2::new(x - 1, y)))
+                            / 2.0;
+                        world.insert(
+                            ColliderBuilder::cuboid(len * half, half),
+                            center,
+                            terrain,
+                            Target::Terrain,
+                        );
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+            for x in 0..puzzle.size.x {
+                let cell = IVec2::new(x, y);
+                let center = puzzle.tile_center(cell);
+                match puzzle.tile(toggled, cell) {
+                    Tile::Spikes => world.insert(
+                        ColliderBuilder::triangle(
+                            point![-half, -half],
+                            point![half, -half],
+                            point![0.0, half],
+                        ),
+                        center,
+                        terrain,
+                        Target::Terrain,
+                    ),
+                    Tile::Platform => world.insert(
+                        ColliderBuilder::cuboid(half, 1.0),
+                        center + Vec2::Y * (half - 1.0),
+                        terrain,
+                        Target::Terrain,
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        for (&cell, crystal) in puzzle.crystals.iter() {
+            if !puzzle.is_active(crystal, toggled) {
+                continue;
+            }
+            let groups = if crystal.color == LightColor::Blue {
+                CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL & !GroupLabel::BLUE_RAY)
+            } else {
+                terrain
+            };
+            world.insert(
+                ColliderBuilder::cuboid(half, half),
+                puzzle.tile_center(cell),
+                groups,
+                Target::Terrain,
+            );
+        }
+
+        for (i, button) in puzzle.buttons.iter().enumerate() {
+            world.insert(
+                ColliderBuilder::cuboid(half, half).sensor(true),
+                button.center,
+                CollisionGroups::new(
+                    GroupLabel::LIGHT_SENSOR,
+                    GroupLabel::LIGHT_RAY | GroupLabel::WHITE_RAY | GroupLabel::BLUE_RAY,
+                ),
+                Target::Button(i),
+            );
+        }
+
+        for &(a, b) in white_beams.iter() {
+            world.insert(
+                ColliderBuilder::cuboid(a.distance(b) / 2.0, 0.5)
+                    .rotation((b - a).to_angle())
+                    .sensor(true),
+                a.midpoint(b),
+                CollisionGroups::new(
+                    GroupLabel::WHITE_RAY,
+                    GroupLabel::TERRAIN
+                        | GroupLabel::LIGHT_SENSOR
+                        | GroupLabel::LIGHT_RAY
+                        | GroupLabel::BLUE_RAY,
+                ),
+                Target::WhiteBeam,
+            );
+        }
+
+        world.query_pipeline.update(&world.colliders);
+        world
+    }
+
+    fn insert(
+        &mut self,
+        collider: ColliderBuilder,
+        pos: Vec2,
+        groups: CollisionGroups,
+        target: Target,
+    ) {
+        let handle = self.colliders.insert(
+            collider
+                .translation(vector![pos.x, pos.y])
+                .collision_groups(InteractionGroups::new(groups.memberships, groups.filters)),
+        );
+        self.targets.insert(handle, target);
+    }
+
+    /// Casts a light ray of `color`, for use with [`trace_light_ray`].
+    fn cast_light_ray(
+        &self,
+        color: LightColor,
+        ray_pos: Vec2,
+        ray_dir: Vec2,
+        max_distance: f32,
+        exclude: Option<ColliderHandle>,
+    ) -> Option<LightHit<ColliderHandle>> {
+        let groups = color.ray_collision_groups();
+        let mut filter =
+            QueryFilter::new().groups(InteractionGroups::new(groups.memberships, groups.filters));
+        if let Some(handle) = exclude {
+            filter = filter.exclude_collider(handle);
+        }
+        let ray = Ray::new(point![ray_pos.x, ray_pos.y], vector![ray_dir.x, ray_dir.y]);
+        let (handle, intersection) = self.query_pipeline.cast_ray_and_get_normal(
+            &self.bodies,
+            &self.colliders,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )?;
+        let point = ray.point_at(intersection.time_of_impact);
+        Some(LightHit {
+            target: handle,
+            point: Vec2::new(point.x, point.y),
+            normal: Vec2::new(intersection.normal.x, intersection.normal.y),
+            time_of_impact: intersection.time_of_impact,
+        })
+    }
+}
+
+/// A beam shot by the player.
+#[derive(Clone)]
+struct Shot {
+    color: LightColor,
+    spot: IVec2,
+    /// Counterclockwise from the right, in degrees
+    angle: f32,
+    buttons: Vec<usize>,
+}
+
+/// A node of the search: the crystals toggled so far, and the shots it took to get there.
+struct SearchState {
+    toggled: Toggled,
+    /// A bit for each color that has been shot
+    used: u8,
+    white_beams: Vec<(Vec2, Vec2)>,
+    shots: Vec<Shot>,
+}
+
+/// What the search found in a level.
+struct Solution {
+    lit_buttons: HashSet<usize>,
+    initial_exits: HashSet<Exit>,
+    /// The shortest sequence of shots that reaches a new exit, and the new exits it reaches
+    shots: Option<(Vec<Shot>, Vec<Exit>)>,
+    /// Whether the search gave up before trying every combination of shots
+    exhausted: bool,
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("Usage: lightborne-solve <project.ldtk> [level]...");
+        return ExitCode::from(2);
+    };
+    let level_filter: Vec<String> = args.collect();
+
+    let project: LdtkJson = match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()))
+    {
+        Ok(project) => project,
+        Err(err) => {
+            println!("{path}: failed to load project: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let levels = project
+        .levels
+        .iter()
+        .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+        .filter(|level| level_filter.is_empty() || level_filter.contains(&level.identifier));
+
+    let mut failed = false;
+    for level in levels {
+        let name = &level.identifier;
+        let puzzle = match load_puzzle(level) {
+            Ok(puzzle) => puzzle,
+            Err(err) => {
+                println!("{name}: skipped, {err}");
+                continue;
+            }
+        };
+        failed |= !report(name, &puzzle, &solve(&puzzle));
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn load_puzzle(level: &Level) -> Result<Puzzle, String> {
+    let layers = level
+        .layer_instances
+        .as_ref()
+        .ok_or("external levels are not supported")?;
+    let layer = |identifier: &str| layers.iter().find(|layer| layer.identifier == identifier);
+    let terrain = layer("Terrain").ok_or("missing the Terrain layer")?;
+    let crystalmap = layer("Crystalmap");
+    let entities = layer("Entities").map_or(&[][..], |layer| &layer.entity_instances[..]);
+    let grid_size = terrain.grid_size as f32;
+    let size = IVec2::new(terrain.c_wid, terrain.c_hei);
+
+    let start = entities
+        .iter()
+        .find(|entity| entity.identifier == "Start")
+        .ok_or("missing a Start flag")?;
+    let start = (entity_center(start) / grid_size).floor().as_ivec2();
+
+    let mut keys = Vec::new();
+    let mut key_index = |key: CrystalKey| match keys.iter().position(|k| *k == key) {
+        Some(i) => i,
+        None => {
+            keys.push(key);
+            keys.len() - 1
+        }
+    };
+
+    let mut crystals = HashMap::new();
+    for crystal in ldtk_crystals(terrain, crystalmap, entities) {
+        crystals.insert(
+            crystal.cell,
+            PuzzleCrystal {
+                color: crystal.key.color,
+                init_active: crystal.active,
+                key: key_index(crystal.key),
+            },
+        );
+    }
+
+    let mut buttons = Vec::new();
+    for entity in entities
+        .iter()
+        .filter(|entity| entity.identifier == "Button")
+    {
+        let key = button_crystal_key(entity)
+            .ok_or_else(|| format!("Button at {} has no valid light_color or id", entity.grid))?;
+        let center = entity_center(entity);
+        buttons.push(PuzzleButton {
+            grid: entity.grid,
+            center: Vec2::new(center.x, -center.y),
+            key: key_index(key),
+        });
+    }
+
+    if keys.len() > Toggled::BITS as usize {
+        return Err(format!("too many crystal IDs ({})", keys.len()));
+    }
+
+    Ok(Puzzle {
+        grid_size,
+        jump_height: jump_height(grid_size),
+        size,
+        terrain: terrain.int_grid_csv.clone(),
+        crystals,
+        buttons,
+        keys,
+        start,
+    })
+}
+
+/// Searches every combination of shots, fewest shots first. Each color can be shot once, as the
+/// player's light inventory only refills when the level is reset.
+fn solve(puzzle: &Puzzle) -> Solution {
+    let (_, initial_exits) = puzzle.reachable(0);
+    let mut solution = Solution {
+        lit_buttons: HashSet::new(),
+        initial_exits,
+        shots: None,
+        exhausted: false,
+    };
+
+    let max_distance = puzzle.size.as_vec2().length() * puzzle.grid_size;
+    let mut visited = HashSet::from([(0, 0)]);
+    let mut queue = VecDeque::from([SearchState {
+        toggled: 0,
+        used: 0,
+        white_beams: Vec::new(),
+        shots: Vec::new(),
+    }]);
+
+    while let Some(state) = queue.pop_front() {
+        let (spots, exits) = puzzle.reachable(state.toggled);
+        if solution.shots.is_none() {
+            let mut new_exits: Vec<Exit> = exits
+                .iter()
+                .filter(|exit| !solution.initial_exits.iter().any(|i| exit.is_near(i)))
+                .copied()
+                .collect();
+            if !new_exits.is_empty() {
+                new_exits.sort();
+                solution.shots = Some((state.shots.clone(), new_exits));
+            }
+        }
+
+        let world = PuzzleWorld::new(puzzle, state.toggled, &state.white_beams);
+        for color in (0..LightColor::LENGTH).map(LightColor::from_usize) {
+            let color_bit = 1 << color.into_usize();
+            if state.used & color_bit != 0 {
+                continue;
+            }
+            let max_distance = max_distance * (color.num_bounces() + 1) as f32;
+            for &spot in spots.iter() {
+                let origin = puzzle.shot_origin(spot);
+                for i in 0..ANGLE_SAMPLES {
+                    let angle = TAU * i as f32 / ANGLE_SAMPLES as f32;
+                    let path = trace_light_ray(
+                        origin,
+                        Vec2::from_angle(angle),
+                        color,
+                        max_distance,
+                        |ray_pos, ray_dir, max_distance, exclude| {
+                            world.cast_light_ray(color, ray_pos, ray_dir, max_distance, exclude)
+                        },
+                        |_| false,
+                    );
+
+                    let mut buttons: Vec<usize> = path
+                        .hits
+                        .iter()
+                        .filter_map(|handle| match world.targets.get(handle) {
+                            Some(Target::Button(button)) => Some(*button),
+                            _ => None,
+                        })
+                        .collect();
+                    buttons.sort();
+                    buttons.dedup();
+                    if buttons.is_empty() {
+                        continue;
+                    }
+                    solution.lit_buttons.extend(buttons.iter().copied());
+
+                    // every button toggles its crystals, so two buttons for the same crystals
+                    // cancel out
+                    let toggled = buttons.iter().fold(state.toggled, |toggled, &button| {
+                        toggled ^ (1 << puzzle.buttons[button].key)
+                    });
+                    let used = state.used | color_bit;
+                    if !visited.insert((toggled, used)) {
+                        continue;
+                    }
+                    if visited.len() > MAX_STATES {
+                        solution.exhausted = true;
+                        return solution;
+                    }
+
+                    let mut white_beams = state.white_beams.clone();
+                    if color == LightColor::White {
+                        white_beams.extend(path.points.windows(2).map(|pts| (pts[0], pts[1])));
+                    }
+                    let mut shots = state.shots.clone();
+                    shots.push(Shot {
+                        color,
+                        spot,
+                        angle: angle.to_degrees(),
+                        buttons,
+                    });
+                    queue.push_back(SearchState {
+                        toggled,
+                        used,
+                        white_beams,
+                        shots,
+                    });
+                }
+            }
+        }
+    }
+
+    solution
+}
+
+/// Prints what was found in a level, returning whether its puzzle looks solvable.
+fn report(name: &str, puzzle: &Puzzle, solution: &Solution) -> bool {
+    if puzzle.buttons.is_empty() {
+        println!("{name}: no buttons");
+        return true;
+    }
+    println!(
+        "{name}: {} button(s), {} set(s) of crystals",
+        puzzle.buttons.len(),
+        puzzle.keys.len()
+    );
+
+    let mut ok = true;
+    for (i, button) in puzzle.buttons.iter().enumerate() {
+        let key = &puzzle.keys[button.key];
+        if solution.lit_buttons.contains(&i) {
+            println!("  Button at {} ({key}) can be lit", button.grid);
+        } else {
+            println!("  Button at {} ({key}) can never be lit", button.grid);
+            ok = false;
+        }
+    }
+
+    let mut initial_exits: Vec<_> = solution.initial_exits.iter().collect();
+    initial_exits.sort();
+    for exit in initial_exits.iter() {
+        println!("  Reachable without shooting: {exit}");
+    }
+    let edges: HashSet<_> = initial_exits
+        .iter()
+        .map(|exit| mem::discriminant(*exit))
+        .collect();
+    if edges.len() > 1 {
+        println!("  Warning: more than one edge of the level is reachable without shooting");
+    }
+
+    match &solution.shots {
+        Some((shots, exits)) => {
+            println!("  Solved in {} shot(s):", shots.len());
+            for (i, shot) in shots.iter().enumerate() {
+                let buttons: Vec<String> = shot
+                    .buttons
+                    .iter()
+                    .map(|&button| puzzle.buttons[button].grid.to_string())
+                    .collect();
+                println!(
+                    "    {}. {:?} from {} at {:.1} degrees, lighting {}",
+                    i + 1,
+                    shot.color,
+                    shot.spot,
+                    shot.angle,
+                    buttons.join(", ")
+                );
+            }
+            for exit in exits {
+                println!("    reaches the {exit}");
+            }
+        }
+        None if solution.exhausted => {
+            println!("  Gave up after {MAX_STATES} states without reaching a new exit");
+            ok = false;
+        }
+        None => {
+            println!("  No sequence of shots reaches a new exit");
+            ok = false;
+        }
+    }
+    ok
+}
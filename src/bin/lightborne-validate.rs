@@ -0,0 +1,307 @@
+//! Checks Ldtk projects for mistakes that would otherwise only be found at runtime, usually
+//! through a panic. Exits with a non-zero code if any problems are found.
+//!
+//! Usage: `cargo run --bin lightborne-validate -- assets/levels/lightborne.ldtk`
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    process::ExitCode,
+};
+
+use bevy::math::IVec2;
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, LayerInstance, LdtkJson, Level},
+    prelude::LdtkFields,
+};
+use lightborne::{
+    level::{
+        error::check_entity_instance,
+        ldtk_data::{
+            button_crystal_key, cell_grid, entity_bounds, ldtk_crystals, CrystalKey, CrystalKeyId,
+        },
+        registry::{is_registered_entity, registered_int_grid_values},
+    },
+    light::LightColor,
+};
+
+/// Entities that are allowed to overlap with others: Lyra spawns on top of the start flag,
+/// crystal groups and zones are drawn around other things, and doors and warps are often placed
+/// on top of the entity that leads back through them.
+const OVERLAPPING_IDENTIFIERS: [&str; 6] =
+    ["Lyra", "CrystalGroup", "Door", "Warp", "Exit", "WindZone"];
+
+/// A problem found in a level.
+struct Problem {
+    level: String,
+    message: String,
+}
+
+/// The crystals and buttons of a level, gathered so that they can be checked against those of
+/// every level linking the same IDs.
+#[derive(Default)]
+struct LevelCrystals {
+    crystals: BTreeSet<CrystalKey>,
+    buttons: Vec<(CrystalKey, IVec2)>,
+    /// The white crystal IDs linked across the world with the `world_crystal_ids` level field
+    world_crystal_ids: HashSet<i32>,
+}
+
+impl LevelCrystals {
+    fn is_linked(&self, key: &CrystalKey) -> bool {
+        key.color == LightColor::White
+            && matches!(key.id, CrystalKeyId::Painted(id) if self.world_crystal_ids.contains(&id))
+    }
+}
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("Usage: lightborne-validate <project.ldtk>...");
+        return ExitCode::from(2);
+    }
+
+    let mut num_problems = 0;
+    for path in paths.iter() {
+        let project = match load_project(path) {
+            Ok(project) => project,
+            Err(err) => {
+                println!("{path}: failed to load project: {err}");
+                num_problems += 1;
+                continue;
+            }
+        };
+        let problems = validate_project(&project);
+        for Problem { level, message } in problems.iter() {
+            println!("{path}: {level}: {message}");
+        }
+        num_problems += problems.len();
+    }
+
+    if num_problems == 0 {
+        println!("No problems found");
+        ExitCode::SUCCESS
+    } else {
+        println!("Found {num_problems} problem(s)");
+        ExitCode::FAILURE
+    }
+}
+
+fn load_project(path: &str) -> Result<LdtkJson, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn validate_project(project: &LdtkJson) -> Vec<Problem> {
+    let levels: Vec<&Level> = project
+        .levels
+        .iter()
+        .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut level_crystals = Vec::with_capacity(levels.len());
+    for level in levels.iter() {
+        let mut report = |message: String| {
+            problems.push(Problem {
+                level: level.identifier.clone(),
+                message,
+            })
+        };
+        let Some(layers) = &level.layer_instances else {
+            report("level has no layers, external levels are not supported".to_string());
+            level_crystals.push(LevelCrystals::default());
+            continue;
+        };
+        let layer = |identifier: &str| layers.iter().find(|layer| layer.identifier == identifier);
+        let entities = layer("Entities").map_or(&[][..], |layer| &layer.entity_instances[..]);
+
+        if !entities.iter().any(|entity| entity.identifier == "Start") {
+            report("missing a Start flag, so the player can't respawn".to_string());
+        }
+        for identifier in ["Terrain", "Crystalmap"] {
+            let Some(layer) = layer(identifier) else {
+                report(format!("missing the {identifier} layer"));
+                continue;
+            };
+            validate_int_grid(layer, registered_int_grid_values(identifier), &mut report);
+        }
+        for entity in entities.iter() {
+            validate_entity_fields(entity, &mut report);
+        }
+        validate_overlaps(entities, &mut report);
+
+        level_crystals.push(gather_crystals(
+            level,
+            layer("Terrain"),
+            layer("Crystalmap"),
+            entities,
+        ));
+    }
+
+    // linked crystals can be toggled by buttons in any level linking the same ID
+    let linked_crystals: HashSet<&CrystalKey> = level_crystals
+        .iter()
+        .flat_map(|crystals| {
+            crystals
+                .crystals
+                .iter()
+                .filter(|key| crystals.is_linked(key))
+        })
+        .collect();
+    let linked_buttons: HashSet<&CrystalKey> = level_crystals
+        .iter()
+        .flat_map(|crystals| {
+            crystals
+                .buttons
+                .iter()
+                .map(|(key, _)| key)
+                .filter(|key| crystals.is_linked(key))
+        })
+        .collect();
+
+    for (level, crystals) in levels.iter().zip(level_crystals.iter()) {
+        let mut report = |message: String| {
+            problems.push(Problem {
+                level: level.identifier.clone(),
+                message,
+            })
+        };
+        for (key, grid) in crystals.buttons.iter() {
+            let has_crystals = if crystals.is_linked(key) {
+                linked_crystals.contains(key)
+            } else {
+                crystals.crystals.contains(key)
+            };
+            if !has_crystals {
+                report(format!(
+                    "Button at {grid} toggles {key}, but there are none"
+                ));
+            }
+        }
+        for key in crystals.crystals.iter() {
+            let has_button = if crystals.is_linked(key) {
+                linked_buttons.contains(key)
+            } else {
+                crystals.buttons.iter().any(|(button, _)| button == key)
+            };
+            if !has_button {
+                report(format!("no Button toggles the {key}"));
+            }
+        }
+    }
+
+    // doors can lead to any level, so their destinations are checked against the whole project
+    let entity_iids: HashSet<&str> = levels
+        .iter()
+        .flat_map(|level| level.layer_instances.iter().flatten())
+        .flat_map(|layer| layer.entity_instances.iter())
+        .map(|entity| entity.iid.as_str())
+        .collect();
+    for level in levels.iter() {
+        let doors = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| layer.entity_instances.iter())
+            .filter(|entity| matches!(entity.identifier.as_str(), "Door" | "Warp"));
+        for door in doors {
+            let Ok(destination) = door.get_entity_ref_field("destination") else {
+                continue;
+            };
+            if !entity_iids.contains(destination.entity_iid.as_str()) {
+                problems.push(Problem {
+                    level: level.identifier.clone(),
+                    message: format!(
+                        "{} at {} leads to an entity that doesn't exist",
+                        door.identifier, door.grid
+                    ),
+                });
+            }
+        }
+    }
+
+    problems
+}
+
+fn validate_int_grid(layer: &LayerInstance, valid_values: &[i32], report: &mut impl FnMut(String)) {
+    let mut unknown: HashMap<i32, IVec2> = HashMap::new();
+    for (i, &value) in layer.int_grid_csv.iter().enumerate() {
+        if value != 0 && !valid_values.contains(&value) {
+            unknown.entry(value).or_insert(cell_grid(layer, i));
+        }
+    }
+
+    let mut unknown: Vec<_> = unknown.into_iter().collect();
+    unknown.sort_by_key(|(value, _)| *value);
+    for (value, grid) in unknown {
+        report(format!(
+            "unknown value {value} on the {} layer, first at {grid}",
+            layer.identifier
+        ));
+    }
+}
+
+/// Checks the fields that the game expects on each entity, with the same check as the game.
+fn validate_entity_fields(entity: &EntityInstance, report: &mut impl FnMut(String)) {
+    if !is_registered_entity(&entity.identifier) {
+        report(format!(
+            "unknown entity {} at {}",
+            entity.identifier, entity.grid
+        ));
+        return;
+    }
+    if let Err(kind) = check_entity_instance(entity) {
+        report(format!("{} at {}: {kind}", entity.identifier, entity.grid));
+    }
+}
+
+fn validate_overlaps(entities: &[EntityInstance], report: &mut impl FnMut(String)) {
+    let solid: Vec<&EntityInstance> = entities
+        .iter()
+        .filter(|entity| !OVERLAPPING_IDENTIFIERS.contains(&entity.identifier.as_str()))
+        .collect();
+    for (i, a) in solid.iter().enumerate() {
+        for b in solid[i + 1..].iter() {
+            let (a_min, a_max) = entity_bounds(a);
+            let (b_min, b_max) = entity_bounds(b);
+            if a_min.cmplt(b_max).all() && b_min.cmplt(a_max).all() {
+                report(format!(
+                    "{} at {} overlaps {} at {}",
+                    a.identifier, a.grid, b.identifier, b.grid
+                ));
+            }
+        }
+    }
+}
+
+/// Collects the crystals of a level and the crystals each of its buttons toggle.
+fn gather_crystals(
+    level: &Level,
+    terrain: Option<&LayerInstance>,
+    crystalmap: Option<&LayerInstance>,
+    entities: &[EntityInstance],
+) -> LevelCrystals {
+    let crystals = terrain
+        .map(|terrain| ldtk_crystals(terrain, crystalmap, entities))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|crystal| crystal.key)
+        .collect();
+
+    let buttons = entities
+        .iter()
+        .filter(|entity| entity.identifier == "Button")
+        .filter_map(|entity| Some((button_crystal_key(entity)?, entity.grid)))
+        .collect();
+
+    let world_crystal_ids = level
+        .get_maybe_ints_field("world_crystal_ids")
+        .map(|ids| ids.iter().flatten().copied().collect())
+        .unwrap_or_default();
+
+    LevelCrystals {
+        crystals,
+        buttons,
+        world_crystal_ids,
+    }
+}
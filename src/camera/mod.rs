@@ -65,6 +65,8 @@ fn setup_camera(mut commands: Commands) {
         },
         Tonemapping::TonyMcMapface,
         // Bloom::default(),
+        // the camera hears positional sounds, e.g. crystals toggling
+        SpatialListener::new(CAMERA_WIDTH / 2.),
         projection.clone(),
         Transform::default(),
     ));
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::light::LightColor;
 
 pub struct ConfigPlugin;
 
@@ -24,6 +26,14 @@ impl Plugin for ConfigPlugin {
 pub struct Config {
     pub level_config: LevelConfig,
     pub debug_config: DebugConfig,
+    #[serde(default)]
+    pub dash_config: DashConfig,
+    #[serde(default)]
+    pub health_config: HealthConfig,
+    #[serde(default)]
+    pub crush_config: CrushConfig,
+    #[serde(default)]
+    pub save_config: SaveConfig,
 }
 
 #[derive(Deserialize)]
@@ -33,6 +43,139 @@ pub struct DebugConfig {
 
 #[derive(Deserialize)]
 pub struct LevelConfig {
+    #[serde(default)]
     pub level_index: usize,
+    #[serde(default)]
     pub level_path: String,
+    /// Ldtk projects played one after another in a single run. If this is empty, only the project
+    /// at `level_path` is played, starting at `level_index`.
+    #[serde(default)]
+    pub playlist: Vec<PlaylistEntry>,
+}
+
+impl LevelConfig {
+    /// The projects to play, in order, falling back to `level_path` if there is no playlist.
+    pub fn playlist(&self) -> Vec<PlaylistEntry> {
+        if !self.playlist.is_empty() {
+            return self.playlist.clone();
+        }
+        vec![PlaylistEntry {
+            path: self.level_path.clone(),
+            first_level: self.level_index,
+            last_level: None,
+        }]
+    }
+}
+
+/// A project in the [`LevelConfig`]'s playlist, and the range of its levels that are played.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PlaylistEntry {
+    /// The path of the Ldtk project, relative to the assets folder
+    pub path: String,
+    /// The index of the level the player starts in
+    #[serde(default)]
+    pub first_level: usize,
+    /// The index of the level that moves on to the next project once the player leaves it. If
+    /// this isn't set, the rest of the run is played in this project.
+    pub last_level: Option<usize>,
+}
+
+/// Tuning for the player's dash. Durations are measured in [`FixedUpdate`] steps, the same as the
+/// rest of the player's movement.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DashConfig {
+    /// How far the player travels over the course of a dash, in pixels
+    pub distance: f32,
+    pub duration_ticks: u32,
+    /// The time after a dash ends before the player can dash again
+    pub cooldown_ticks: u32,
+}
+
+impl Default for DashConfig {
+    fn default() -> Self {
+        Self {
+            distance: 40.0,
+            duration_ticks: 10,
+            cooldown_ticks: 20,
+        }
+    }
+}
+
+/// How punishing hazards are.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Touching any hazard kills the player
+    #[default]
+    OneHit,
+    /// Hazards take away some of the player's health, killing them once it runs out
+    Health,
+}
+
+/// Settings for the player's health, only used with [`Difficulty::Health`].
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub difficulty: Difficulty,
+    pub max_health: u32,
+    /// How long the player can't be hurt again after taking damage
+    pub invulnerability_secs: f32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::OneHit,
+            max_health: 3,
+            invulnerability_secs: 1.0,
+        }
+    }
+}
+
+/// What happens when a crystal turns solid while the player is inside of it.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrushBehavior {
+    /// The player dies, with [`DeathCause::Crushed`](crate::player::DeathCause::Crushed)
+    Kill,
+    /// The player is pushed out to the nearest free spot, dying if there is none
+    PushOut,
+    /// The crystal waits until the player is out of the way before turning solid
+    #[default]
+    Defer,
+}
+
+/// The [`CrushBehavior`] of crystals of each color.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CrushConfig {
+    pub red: CrushBehavior,
+    pub green: CrushBehavior,
+    pub white: CrushBehavior,
+    pub blue: CrushBehavior,
+}
+
+impl CrushConfig {
+    pub fn behavior(&self, color: LightColor) -> CrushBehavior {
+        match color {
+            LightColor::Red => self.red,
+            LightColor::Green => self.green,
+            LightColor::White => self.white,
+            LightColor::Blue => self.blue,
+        }
+    }
+}
+
+/// Settings for the [`SavePlugin`](crate::save::SavePlugin).
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SaveConfig {
+    /// Whether progress is loaded from and written to the save file. Turn this off to always start
+    /// at `level_index` while working on levels.
+    pub enabled: bool,
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
 }
@@ -0,0 +1,265 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    light::BlocksLight,
+    lighting::occluder::ColliderBasedOccluder,
+    player::{
+        movement::{Facing, PlayerMovement, PLAYER_CROUCH_HALF_HEIGHT, PLAYER_HALF_HEIGHT},
+        PlayerMarker,
+    },
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::{error::LevelDataCheck, LevelSystems};
+
+/// How close the center of a [`Carryable`] needs to be to the player's center to be picked up.
+const PICKUP_RANGE: f32 = 14.0;
+/// The gap between the top of the player's head and the center of the object they are carrying.
+const CARRY_HEIGHT: f32 = 5.0;
+/// The velocity, in pixels per second, of a thrown [`Carryable`] when the player is facing right.
+const THROW_VEL: Vec2 = Vec2::new(120.0, 60.0);
+
+/// [`Plugin`] for [`Carryable`]s, which the player can pick up with E and carry above their head.
+/// Pressing E again throws the object in the direction the player is facing, or gently drops it
+/// if the player is crouching.
+pub struct CarryablePlugin;
+
+impl Plugin for CarryablePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<CarryableBundle>("Carryable")
+            .add_systems(PreUpdate, init_carryables.in_set(LevelSystems::Processing))
+            .add_systems(
+                Update,
+                (
+                    pick_up_or_throw.run_if(input_just_pressed(KeyCode::KeyE)),
+                    carry_objects,
+                )
+                    .chain()
+                    .in_set(LevelSystems::Simulation),
+            )
+            .add_systems(FixedUpdate, reset_carryables.run_if(on_event::<ResetLevel>));
+    }
+}
+
+/// [`Component`] for physics objects that the player can pick up and throw. Carryables are
+/// terrain, so the player can stand on them and they reflect light, unless they are not
+/// `reflective`, in which case they block light with [`BlocksLight`].
+#[derive(Default, Component)]
+pub struct Carryable {
+    reflective: bool,
+    /// Where the carryable is placed in its level, relative to its parent
+    home: Vec3,
+}
+
+impl From<&EntityInstance> for Carryable {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        // a missing field is reported by the carryable's `LevelDataCheck`
+        let reflective = entity_instance
+            .get_bool_field("reflective")
+            .is_ok_and(|reflective| *reflective);
+
+        Carryable {
+            reflective,
+            home: Vec3::ZERO,
+        }
+    }
+}
+
+/// Marker [`Component`] for the [`Carryable`] the player is currently carrying.
+#[derive(Component)]
+pub struct Carried;
+
+/// [`Bundle`] registered with Ldtk to spawn carryables.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CarryableBundle {
+    #[from_entity_instance]
+    carryable: Carryable,
+    #[from_entity_instance]
+    physics: CarryablePhysicsBundle,
+    #[from_entity_instance]
+    sprite: CarryableSprite,
+    #[from_entity_instance]
+    check: LevelDataCheck,
+}
+
+/// [`Bundle`] that makes carryables fall, collide with terrain and cast shadows.
+#[derive(Default, Bundle)]
+struct CarryablePhysicsBundle {
+    collider: Collider,
+    rigid_body: RigidBody,
+    locked_axes: LockedAxes,
+    velocity: Velocity,
+    collision_groups: CollisionGroups,
+    occluder: ColliderBasedOccluder,
+}
+
+impl From<&EntityInstance> for CarryablePhysicsBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CarryablePhysicsBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            rigid_body: RigidBody::Dynamic,
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            velocity: Velocity::zero(),
+            collision_groups: CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL),
+            occluder: ColliderBasedOccluder::default(),
+        }
+    }
+}
+
+/// Placeholder [`Sprite`] for carryables: silver mirrors and brown crates.
+#[derive(Default, Bundle)]
+struct CarryableSprite {
+    sprite: Sprite,
+}
+
+impl From<&EntityInstance> for CarryableSprite {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let reflective = entity_instance
+            .get_bool_field("reflective")
+            .is_ok_and(|reflective| *reflective);
+        CarryableSprite {
+            sprite: Sprite {
+                color: if reflective {
+                    Color::srgb(0.75, 0.85, 0.95)
+                } else {
+                    Color::srgb(0.45, 0.3, 0.2)
+                },
+                custom_size: Some(Vec2::new(
+                    entity_instance.width as f32,
+                    entity_instance.height as f32,
+                )),
+                ..default()
+            },
+        }
+    }
+}
+
+/// [`System`] that remembers where each carryable was placed, so that it can be put back when the
+/// level is reset, and makes non-reflective carryables block light.
+fn init_carryables(
+    mut commands: Commands,
+    mut q_carryables: Query<(Entity, &mut Carryable, &Transform), Added<Carryable>>,
+) {
+    for (entity, mut carryable, transform) in q_carryables.iter_mut() {
+        carryable.home = transform.translation;
+        if !carryable.reflective {
+            commands.entity(entity).insert(BlocksLight);
+        }
+    }
+}
+
+/// [`System`] that picks up the nearest [`Carryable`] in range, or throws the one being carried.
+fn pick_up_or_throw(
+    mut commands: Commands,
+    q_player: Query<(&GlobalTransform, &Facing, &PlayerMovement), With<PlayerMarker>>,
+    mut q_carryables: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut RigidBody,
+            &mut Velocity,
+            &mut CollisionGroups,
+            Has<Carried>,
+        ),
+        With<Carryable>,
+    >,
+) {
+    let Ok((player_transform, facing, movement)) = q_player.get_single() else {
+        return;
+    };
+
+    for (entity, _, mut body, mut velocity, mut groups, carried) in q_carryables.iter_mut() {
+        if !carried {
+            continue;
+        }
+        *body = RigidBody::Dynamic;
+        *groups = CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL);
+        velocity.linvel = if movement.crouching {
+            Vec2::ZERO
+        } else {
+            Vec2::new(THROW_VEL.x * facing.sign(), THROW_VEL.y)
+        };
+        commands.entity(entity).remove::<Carried>();
+        return;
+    }
+
+    let player_pos = player_transform.translation().truncate();
+    let nearest = q_carryables
+        .iter_mut()
+        .map(|item| {
+            let distance = item.1.translation().truncate().distance(player_pos);
+            (item, distance)
+        })
+        .filter(|(_, distance)| *distance <= PICKUP_RANGE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    let Some(((entity, _, mut body, mut velocity, mut groups, _), _)) = nearest else {
+        return;
+    };
+
+    *body = RigidBody::KinematicPositionBased;
+    // the carried object stays terrain so that light still reflects off of it, but it shouldn't
+    // stop the player from jumping
+    *groups = CollisionGroups::new(
+        GroupLabel::TERRAIN,
+        GroupLabel::ALL & !GroupLabel::PLAYER_COLLIDER,
+    );
+    velocity.linvel = Vec2::ZERO;
+    commands.entity(entity).insert(Carried);
+}
+
+/// [`System`] that holds the [`Carried`] object above the player's head.
+fn carry_objects(
+    q_player: Query<(&GlobalTransform, &PlayerMovement), With<PlayerMarker>>,
+    mut q_carried: Query<(&mut Transform, &Parent), With<Carried>>,
+    q_parent: Query<&GlobalTransform, Without<Carried>>,
+) {
+    let Ok((player_transform, movement)) = q_player.get_single() else {
+        return;
+    };
+    let Ok((mut transform, parent)) = q_carried.get_single_mut() else {
+        return;
+    };
+    let Ok(parent_transform) = q_parent.get(parent.get()) else {
+        return;
+    };
+
+    let half_height = if movement.crouching {
+        PLAYER_CROUCH_HALF_HEIGHT
+    } else {
+        PLAYER_HALF_HEIGHT
+    };
+    let target = player_transform.translation() + Vec3::Y * (half_height + CARRY_HEIGHT);
+    // carryables are positioned relative to their level
+    let local = parent_transform.affine().inverse().transform_point3(target);
+    transform.translation.x = local.x;
+    transform.translation.y = local.y;
+}
+
+/// [`System`] that puts every [`Carryable`] back where it was placed on a [`ResetLevel`], taking
+/// it out of the player's hands.
+fn reset_carryables(
+    mut commands: Commands,
+    mut q_carryables: Query<(
+        Entity,
+        &Carryable,
+        &mut Transform,
+        &mut RigidBody,
+        &mut Velocity,
+        &mut CollisionGroups,
+    )>,
+) {
+    for (entity, carryable, mut transform, mut body, mut velocity, mut groups) in
+        q_carryables.iter_mut()
+    {
+        transform.translation = carryable.home;
+        *body = RigidBody::Dynamic;
+        *velocity = Velocity::zero();
+        *groups = CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL);
+        commands.entity(entity).remove::<Carried>();
+    }
+}
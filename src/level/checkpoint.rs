@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    lighting::light::PointLighting,
+    player::{movement::PLAYER_HALF_HEIGHT, PlayerHurtMarker},
+    shared::GroupLabel,
+};
+
+use super::{
+    crystal::{Crystal, WorldCrystalLinks},
+    error::LevelDataCheck,
+    CurrentLevel, LevelSystems,
+};
+
+/// [`Plugin`] for [`Checkpoint`]s, which the player activates by walking into them. Once
+/// activated, respawning in that level returns the player to the checkpoint instead of the
+/// [`StartFlag`](super::misc::StartFlag).
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveCheckpoints>()
+            .add_event::<CheckpointTouched>()
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .add_systems(
+                Update,
+                (activate_checkpoints, light_active_checkpoints)
+                    .chain()
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] holding the settings of a checkpoint, set in Ldtk.
+#[derive(Default, Component)]
+pub struct Checkpoint {
+    /// Whether the state of the crystals in the level is saved when the checkpoint is activated,
+    /// and restored on respawn. Otherwise, crystals are reset fully on respawn.
+    snapshot_crystals: bool,
+    /// Where the player respawns, which is the bottom of the checkpoint raised by the player's
+    /// half height
+    respawn_point: Vec2,
+}
+
+impl From<&EntityInstance> for Checkpoint {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        // missing fields are reported by the checkpoint's `LevelDataCheck`
+        let snapshot_crystals = entity_instance
+            .get_bool_field("snapshot_crystals")
+            .is_ok_and(|snapshot_crystals| *snapshot_crystals);
+
+        // checkpoints are pivoted at their bottom center
+        let respawn_point = Vec2::new(
+            entity_instance.world_x.unwrap_or_default() as f32,
+            -entity_instance.world_y.unwrap_or_default() as f32
+                + PLAYER_HALF_HEIGHT
+                // add small height so Lyra is not stuck into the floor
+                + 1.0,
+        );
+
+        Checkpoint {
+            snapshot_crystals,
+            respawn_point,
+        }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn checkpoints. The [`PointLighting`] is only visible
+/// while the checkpoint is the active one in its level.
+#[derive(Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    #[from_entity_instance]
+    checkpoint: Checkpoint,
+    #[from_entity_instance]
+    sensor: CheckpointSensorBundle,
+    #[from_entity_instance]
+    check: LevelDataCheck,
+    point_lighting: PointLighting,
+    visibility: Visibility,
+}
+
+impl Default for CheckpointBundle {
+    fn default() -> Self {
+        Self {
+            checkpoint: Checkpoint::default(),
+            sensor: CheckpointSensorBundle::default(),
+            check: LevelDataCheck::default(),
+            point_lighting: PointLighting {
+                radius: 24.0,
+                color: Vec3::new(1.0, 0.9, 0.6),
+            },
+            visibility: Visibility::Hidden,
+        }
+    }
+}
+
+/// [`Bundle`] for the [`Sensor`] that activates the checkpoint when the player touches it.
+#[derive(Default, Bundle)]
+struct CheckpointSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+    collision_groups: CollisionGroups,
+}
+
+impl From<&EntityInstance> for CheckpointSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CheckpointSensorBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            sensor: Sensor,
+            collision_groups: CollisionGroups::new(GroupLabel::TRIGGER, GroupLabel::PLAYER_SENSOR),
+        }
+    }
+}
+
+/// [`Event`] sent every frame the player touches a checkpoint in the [`CurrentLevel`], including
+/// the one that is already active, which is not reported by [`ActiveCheckpoints`] changing.
+#[derive(Event, Debug, Clone)]
+pub struct CheckpointTouched {
+    pub level_iid: LevelIid,
+    pub checkpoint_iid: EntityIid,
+}
+
+/// The checkpoint that was activated last in a level.
+#[derive(Debug)]
+pub struct ActiveCheckpoint {
+    /// Identifies the checkpoint, as the checkpoint [`Entity`] changes when its level is respawned
+    pub iid: EntityIid,
+    pub respawn_point: Vec2,
+    /// The active state of each crystal in the level when the checkpoint was activated, if the
+    /// checkpoint snapshots crystals
+    pub crystals: Option<HashMap<GridCoords, bool>>,
+    /// The toggled state of each [`WorldCrystalLinks`] ID when the checkpoint was activated, if
+    /// the checkpoint snapshots crystals
+    pub world_crystal_links: Option<HashMap<i32, bool>>,
+}
+
+/// [`Resource`] that stores the last activated checkpoint of each level.
+#[derive(Default, Resource)]
+pub struct ActiveCheckpoints {
+    levels: HashMap<LevelIid, ActiveCheckpoint>,
+}
+
+impl ActiveCheckpoints {
+    pub fn get(&self, level_iid: &LevelIid) -> Option<&ActiveCheckpoint> {
+        self.levels.get(level_iid)
+    }
+
+    pub fn insert(&mut self, level_iid: LevelIid, checkpoint: ActiveCheckpoint) {
+        self.levels.insert(level_iid, checkpoint);
+    }
+}
+
+/// [`System`] that activates checkpoints in the [`CurrentLevel`] when the player touches them,
+/// snapshotting crystal states if the checkpoint asks for it.
+#[allow(clippy::too_many_arguments)]
+pub fn activate_checkpoints(
+    rapier_context: ReadDefaultRapierContext,
+    q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_checkpoints: Query<(Entity, &Checkpoint, &EntityIid)>,
+    q_crystals: Query<(&Crystal, &GridCoords, &Parent)>,
+    q_parent: Query<&Parent, Without<Crystal>>,
+    current_level: Res<CurrentLevel>,
+    mut active_checkpoints: ResMut<ActiveCheckpoints>,
+    links: Res<WorldCrystalLinks>,
+    mut ev_checkpoint_touched: EventWriter<CheckpointTouched>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let Some(level_entity) = current_level.level_entity else {
+        return;
+    };
+
+    for (entity, checkpoint, iid) in q_checkpoints.iter() {
+        if rapier_context.intersection_pair(player, entity) != Some(true)
+            || !current_level.world_box.contains(checkpoint.respawn_point)
+        {
+            continue;
+        }
+        ev_checkpoint_touched.send(CheckpointTouched {
+            level_iid: current_level.level_iid.clone(),
+            checkpoint_iid: iid.clone(),
+        });
+        if active_checkpoints
+            .get(&current_level.level_iid)
+            .is_some_and(|active| active.iid == *iid)
+        {
+            continue;
+        }
+
+        let crystals = checkpoint.snapshot_crystals.then(|| {
+            q_crystals
+                .iter()
+                .filter(|(_, _, parent)| {
+                    q_parent
+                        .get(parent.get())
+                        .is_ok_and(|layer_parent| layer_parent.get() == level_entity)
+                })
+                .map(|(crystal, coords, _)| (*coords, crystal.is_active()))
+                .collect()
+        });
+
+        active_checkpoints.levels.insert(
+            current_level.level_iid.clone(),
+            ActiveCheckpoint {
+                iid: iid.clone(),
+                respawn_point: checkpoint.respawn_point,
+                crystals,
+                world_crystal_links: checkpoint
+                    .snapshot_crystals
+                    .then(|| links.toggled().clone()),
+            },
+        );
+    }
+}
+
+/// [`System`] that shows the light of the active checkpoint in each level, hiding the others.
+pub fn light_active_checkpoints(
+    mut q_checkpoints: Query<(&EntityIid, &mut Visibility), With<Checkpoint>>,
+    active_checkpoints: Res<ActiveCheckpoints>,
+) {
+    for (iid, mut visibility) in q_checkpoints.iter_mut() {
+        let active = active_checkpoints
+            .levels
+            .values()
+            .any(|active| active.iid == *iid);
+        visibility.set_if_neq(if active {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
@@ -1,17 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use bevy::prelude::*;
+use bevy::{
+    audio::{SpatialScale, Volume},
+    ecs::entity::EntityHashSet,
+    prelude::*,
+};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_tilemap::tiles::TileTextureIndex;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    light::LightColor,
+    config::{Config, CrushBehavior},
+    light::{sensor::LightSensor, LightColor},
     lighting::occluder::ColliderBasedOccluder,
+    player::{DeathCause, KillPlayerEvent, PlayerMarker},
     shared::{GroupLabel, ResetLevel},
 };
 
-use super::{CurrentLevel, LevelSystems};
+use super::{
+    checkpoint::ActiveCheckpoints, registry::CRYSTALMAP_VALUES, CurrentLevel, LevelSystems,
+};
 
 /// [`Plugin`] for managing all things related to [`Crystal`]s. This plugin responds to the
 /// addition and removal of [`Activated`] [`Component`]s and updates the sprite and collider of
@@ -22,23 +30,36 @@ impl Plugin for CrystalPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<CrystalToggleEvent>()
             .init_resource::<CrystalCache>()
+            .init_resource::<CrystalSfx>()
+            .init_resource::<WorldCrystalLinks>()
+            .init_resource::<CrystalGroupIds>()
+            .register_ldtk_entity::<CrystalGroupBundle>("CrystalGroup")
             .add_systems(
                 PreUpdate,
                 (
-                    init_crystal_cache_and_ids,
-                    add_crystal_colliders,
+                    (init_crystal_cache_and_ids, add_crystal_colliders).chain(),
                     update_crystal_cache,
+                    resolve_crystal_group_refs,
                 )
                     .in_set(LevelSystems::Processing),
             )
-            .add_systems(Update, on_crystal_changed.in_set(LevelSystems::Simulation))
+            .add_systems(
+                Update,
+                (on_crystal_changed, apply_queued_toggles, solidify_crystals)
+                    .chain()
+                    .in_set(LevelSystems::Simulation),
+            )
+            .add_systems(
+                Update,
+                (animate_crystals, fade_crystal_flashes).in_set(LevelSystems::Simulation),
+            )
             .add_systems(FixedUpdate, reset_crystals.run_if(on_event::<ResetLevel>));
 
         for i in 3..=10 {
             app.register_ldtk_int_cell_for_layer::<CrystalBundle>("Terrain", i);
         }
 
-        for i in 1..=10 {
+        for i in CRYSTALMAP_VALUES {
             app.register_ldtk_int_cell_for_layer::<CrystalIdBundle>("Crystalmap", i);
         }
     }
@@ -58,9 +79,20 @@ pub struct Crystal {
     color: CrystalColor,
     init_active: bool,
     active: bool,
+    /// The [`TileTextureIndex`] of the crystal while active, set once the crystal spawns
+    active_index: u32,
+    /// Whether the crystal belongs to a group in [`WorldCrystalLinks`]
+    linked: bool,
 }
 
-/// Identifier [`Component`] used to label the ID of white crystals
+impl Crystal {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+/// Identifier [`Component`] used to label the ID of white crystals, painted on the legacy
+/// Crystalmap layer. New levels should use [`CrystalGroup`]s instead.
 #[derive(Default, Component, Clone, Copy, PartialEq)]
 pub struct CrystalId(i32);
 
@@ -77,11 +109,136 @@ pub struct CrystalIdBundle {
     id: CrystalId,
 }
 
+/// The first ID given to [`CrystalGroup`]s, after the IDs that can be painted on the Crystalmap
+/// layer.
+const FIRST_CRYSTAL_GROUP_ID: i32 = 11;
+
+/// [`Component`] for crystal group entities, which group every crystal inside of their
+/// rectangle. Buttons toggle a group by referencing it with their `crystal_group` field in Ldtk.
+#[derive(Default, Component)]
+pub struct CrystalGroup {
+    size: Vec2,
+}
+
+impl From<&EntityInstance> for CrystalGroup {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CrystalGroup {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+        }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn crystal groups.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct CrystalGroupBundle {
+    #[from_entity_instance]
+    group: CrystalGroup,
+}
+
+/// Finds the group that a crystal centered at `point` is in, out of `groups` paired with their
+/// bounds. Crystals on the edge of a group are inside of it, and the first group wins when groups
+/// overlap. The validator and solver use this too, so that they group crystals like the game.
+pub fn find_crystal_group<T>(
+    groups: impl IntoIterator<Item = (Rect, T)>,
+    point: Vec2,
+) -> Option<T> {
+    groups
+        .into_iter()
+        .find(|(rect, _)| rect.contains(point))
+        .map(|(_, group)| group)
+}
+
+/// [`Component`] on buttons holding the [`CrystalGroup`] they toggle, if any. Once resolved, the
+/// group's ID replaces the `id` field of the button's [`LightSensor`]. Groups only hold white
+/// crystals, so the reference is ignored on buttons of other colors.
+#[derive(Default, Component)]
+pub struct CrystalGroupRef(Option<EntityIid>);
+
+impl From<&EntityInstance> for CrystalGroupRef {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        CrystalGroupRef(
+            entity_instance
+                .get_maybe_entity_ref_field("crystal_group")
+                .ok()
+                .and_then(Option::as_ref)
+                .map(|reference| EntityIid::new(reference.entity_iid.clone())),
+        )
+    }
+}
+
+/// [`Resource`] that gives every [`CrystalGroup`] an ID, shared by the crystals in the group and
+/// the buttons referencing it. IDs are kept when levels respawn, so there can be any number of
+/// groups without them clashing.
+#[derive(Debug, Default, Resource)]
+pub struct CrystalGroupIds {
+    ids: HashMap<EntityIid, i32>,
+}
+
+impl CrystalGroupIds {
+    fn get_or_insert(&mut self, iid: &EntityIid) -> i32 {
+        let next_id = FIRST_CRYSTAL_GROUP_ID + self.ids.len() as i32;
+        *self.ids.entry(iid.clone()).or_insert(next_id)
+    }
+}
+
+/// [`System`] that points the [`LightSensor`] of white buttons with a [`CrystalGroupRef`] at the
+/// ID of their group.
+fn resolve_crystal_group_refs(
+    mut q_buttons: Query<(&CrystalGroupRef, &mut LightSensor), Added<CrystalGroupRef>>,
+    mut group_ids: ResMut<CrystalGroupIds>,
+) {
+    for (group_ref, mut sensor) in q_buttons.iter_mut() {
+        let Some(iid) = &group_ref.0 else {
+            continue;
+        };
+        if sensor.toggle_color.color != LightColor::White {
+            continue;
+        }
+        sensor.toggle_color.id = group_ids.get_or_insert(iid);
+    }
+}
+
 #[derive(Debug, Default, Resource)]
 pub struct CrystalCache {
     levels: HashMap<LevelIid, HashMap<CrystalColor, Vec<Entity>>>,
 }
 
+/// [`Resource`] tracking the white crystal IDs that are linked across the whole world, set per
+/// level with the `world_crystal_ids` level field in Ldtk. Toggling a linked ID toggles its
+/// crystals in every loaded level that links it, and the toggle is remembered so that crystals
+/// respawned by Ldtk come back in the right state.
+///
+/// On a [`ResetLevel::Switching`], linked crystals keep their state. On a [`ResetLevel::Respawn`],
+/// they are restored to how they were when the player entered the [`CurrentLevel`], or to the
+/// active [`Checkpoint`](super::checkpoint::Checkpoint)'s snapshot if it has one.
+#[derive(Debug, Default, Resource)]
+pub struct WorldCrystalLinks {
+    /// The linked IDs of each level that has spawned
+    levels: HashMap<LevelIid, HashSet<i32>>,
+    /// Whether the crystals of each linked ID are toggled from how they were placed in Ldtk
+    toggled: HashMap<i32, bool>,
+    /// The value of `toggled` when the player entered the current level
+    on_entry: HashMap<i32, bool>,
+}
+
+impl WorldCrystalLinks {
+    pub fn toggled(&self) -> &HashMap<i32, bool> {
+        &self.toggled
+    }
+
+    fn is_toggled(&self, id: i32) -> bool {
+        self.toggled.get(&id).copied().unwrap_or(false)
+    }
+
+    fn is_linked(&self, level_iid: &LevelIid, color: CrystalColor) -> bool {
+        color.color == LightColor::White
+            && self
+                .levels
+                .get(level_iid)
+                .is_some_and(|ids| ids.contains(&color.id))
+    }
+}
+
 fn update_crystal_cache(
     mut ev_level: EventReader<LevelEvent>,
     mut crystal_cache: ResMut<CrystalCache>,
@@ -97,14 +254,22 @@ fn update_crystal_cache(
 }
 
 /// System that will initialize all the crystals, storing their entities in the appropriate level
-/// -> crystal color location in the crystal cache.
+/// -> crystal color location in the crystal cache. The ID of a crystal comes from the
+/// [`CrystalGroup`] it is in, falling back to the Crystalmap layer. Crystals of a toggled
+/// [`WorldCrystalLinks`] ID start in their toggled state.
+#[allow(clippy::too_many_arguments)]
 fn init_crystal_cache_and_ids(
     mut commands: Commands,
     q_crystal_id: Query<(&GridCoords, &Parent, &CrystalId), (Added<CrystalId>, Without<Crystal>)>,
-    mut q_crystals: Query<(Entity, &GridCoords, &Parent, &mut Crystal), Added<Crystal>>,
+    q_crystal_groups: Query<(&CrystalGroup, &EntityIid, &Transform, &Parent), Added<CrystalGroup>>,
+    mut q_crystals: Query<(Entity, &GridCoords, &Transform, &Parent, &mut Crystal), Added<Crystal>>,
     q_level_iid: Query<&LevelIid>,
     q_parent: Query<&Parent, (Without<CrystalId>, Without<Crystal>)>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
     mut crystal_cache: ResMut<CrystalCache>,
+    mut links: ResMut<WorldCrystalLinks>,
+    mut group_ids: ResMut<CrystalGroupIds>,
 ) {
     if q_crystals.is_empty() {
         return;
@@ -127,7 +292,25 @@ fn init_crystal_cache_and_ids(
         commands.entity(**parent).insert(Visibility::Hidden);
     }
 
-    for (entity, coord, parent, mut crystal) in q_crystals.iter_mut() {
+    // Hashmap of crystal group rectangles to their ids, in the same space as the crystals
+    let mut groups_map: HashMap<LevelIid, Vec<(Rect, i32)>> = HashMap::new();
+    for (group, iid, transform, parent) in q_crystal_groups.iter() {
+        let Ok(level_entity) = q_parent.get(**parent) else {
+            continue;
+        };
+        let Ok(level_iid) = q_level_iid.get(**level_entity) else {
+            continue;
+        };
+        groups_map
+            .entry(level_iid.clone())
+            .or_insert(Vec::new())
+            .push((
+                Rect::from_center_size(transform.translation.truncate(), group.size),
+                group_ids.get_or_insert(iid),
+            ));
+    }
+
+    for (entity, coord, transform, parent, mut crystal) in q_crystals.iter_mut() {
         let Ok(level_entity) = q_parent.get(**parent) else {
             continue;
         };
@@ -136,13 +319,23 @@ fn init_crystal_cache_and_ids(
         };
 
         // crystal.color is currently CrystalColor::White with id 0, we need to pull the proper ID
-        // in if it exists
+        // in if it exists. Groups only hold white crystals, so crystals of other colors inside of
+        // a group keep answering their buttons.
+        let group_id = groups_map
+            .get(level_iid)
+            .filter(|_| crystal.color.color == LightColor::White)
+            .and_then(|groups| {
+                find_crystal_group(groups.iter().copied(), transform.translation.truncate())
+            });
         let actual_color = CrystalColor {
             color: crystal.color.color,
-            id: coords_map
-                .get(&level_iid)
-                .and_then(|mp| mp.get(coord))
-                .copied()
+            id: group_id
+                .or_else(|| {
+                    coords_map
+                        .get(&level_iid)
+                        .and_then(|mp| mp.get(coord))
+                        .copied()
+                })
                 .unwrap_or(0),
         };
 
@@ -154,43 +347,63 @@ fn init_crystal_cache_and_ids(
             .or_insert(Vec::new())
             .push(entity);
 
+        if !links.levels.contains_key(level_iid) {
+            let ldtk_project = ldtk_project_assets
+                .get(ldtk_projects.single())
+                .expect("Project should be loaded if level has spawned");
+            let linked_ids = ldtk_project
+                .get_raw_level_by_iid(&level_iid.to_string())
+                .and_then(|level| level.get_maybe_ints_field("world_crystal_ids").ok())
+                .map(|ids| ids.iter().flatten().copied().collect())
+                .unwrap_or_default();
+            links.levels.insert(level_iid.clone(), linked_ids);
+        }
+
         crystal.color = actual_color;
+        crystal.linked = links.is_linked(level_iid, actual_color);
+        if crystal.linked && links.is_toggled(actual_color.id) {
+            crystal.active = !crystal.init_active;
+        }
     }
 }
 
-/// Function to determine whether or not a cell value represents an Active Crystal. Does not use
-/// the modulo operator as future crystal cell values need not necessarily follow the same pattern
-/// in the future.
-fn is_crystal_active(cell_value: IntGridCell) -> bool {
+/// Function to determine whether or not a cell value represents an Active Crystal, returning
+/// [`None`] if the value isn't a crystal. Does not use the modulo operator as future crystal cell
+/// values need not necessarily follow the same pattern in the future.
+pub fn is_crystal_active(cell_value: IntGridCell) -> Option<bool> {
     match cell_value.value {
-        3 | 5 | 7 | 9 => true,
-        4 | 6 | 8 | 10 => false,
-        _ => panic!("Cell value does not correspond to crystal!"),
+        3 | 5 | 7 | 9 => Some(true),
+        4 | 6 | 8 | 10 => Some(false),
+        _ => None,
     }
 }
 
-/// Function to determine the base color of the crystal.
-fn crystal_color(cell_value: IntGridCell) -> LightColor {
+/// Function to determine the base color of the crystal, returning [`None`] if the value isn't a
+/// crystal.
+pub fn crystal_color(cell_value: IntGridCell) -> Option<LightColor> {
     match cell_value.value {
-        3 | 4 => LightColor::Red,
-        5 | 6 => LightColor::Green,
-        7 | 8 => LightColor::White,
-        9 | 10 => LightColor::Blue,
-        _ => panic!("Cell value does not correspond to crystal!"),
+        3 | 4 => Some(LightColor::Red),
+        5 | 6 => Some(LightColor::Green),
+        7 | 8 => Some(LightColor::White),
+        9 | 10 => Some(LightColor::Blue),
+        _ => None,
     }
 }
 
+/// [`CrystalBundle`]s are only registered for crystal values, see [`crystal_color`].
 impl From<IntGridCell> for Crystal {
     fn from(cell: IntGridCell) -> Self {
-        let init_active = is_crystal_active(cell);
+        let init_active = is_crystal_active(cell).unwrap_or(false);
 
         Crystal {
             color: CrystalColor {
-                color: crystal_color(cell),
+                color: crystal_color(cell).unwrap_or_default(),
                 id: 0,
             },
             active: init_active,
             init_active,
+            active_index: 0,
+            linked: false,
         }
     }
 }
@@ -216,19 +429,32 @@ impl Default for CrystalBundle {
     }
 }
 
+/// [`System`] that adds colliders to active crystals, and makes sure the tile of each crystal
+/// matches its state, which differs from Ldtk for toggled [`WorldCrystalLinks`] crystals.
 fn add_crystal_colliders(
     mut commands: Commands,
-    q_crystals: Query<(Entity, &IntGridCell), Added<Crystal>>,
+    mut q_crystals: Query<(Entity, &mut Crystal, &mut TileTextureIndex), Added<Crystal>>,
 ) {
-    for (entity, cell) in q_crystals.iter() {
-        if crystal_color(*cell) == LightColor::Blue {
+    for (entity, mut crystal, mut index) in q_crystals.iter_mut() {
+        crystal.active_index = if crystal.init_active {
+            index.0
+        } else {
+            index.0 - CRYSTAL_INDEX_OFFSET
+        };
+        index.0 = if crystal.active {
+            crystal.active_index
+        } else {
+            crystal.active_index + CRYSTAL_INDEX_OFFSET
+        };
+
+        if crystal.color.color == LightColor::Blue {
             let mut collider = commands.entity(entity);
             collider.insert(CollisionGroups::new(
                 GroupLabel::TERRAIN,
                 GroupLabel::ALL & !GroupLabel::BLUE_RAY,
             ));
         }
-        if is_crystal_active(*cell) {
+        if crystal.active {
             let mut collider = commands.entity(entity);
             collider.insert(Collider::cuboid(4.0, 4.0));
         }
@@ -237,18 +463,64 @@ fn add_crystal_colliders(
 
 /// The horizontal offset between active crystals and inactive crystals in the crystal tilemap
 const CRYSTAL_INDEX_OFFSET: u32 = 5;
+/// Half of the width and height of a crystal's [`Collider`].
+const CRYSTAL_HALF_SIZE: f32 = 4.0;
+/// How far the player can be pushed out of a crystal with [`CrushBehavior::PushOut`].
+const MAX_PUSH_OUT_DISTANCE: i32 = 16;
+
+/// Marker [`Component`] for crystals that have been activated, but haven't turned solid yet.
+/// [`solidify_crystals`] turns them solid once it is safe to, depending on the [`CrushBehavior`] of
+/// the crystal's color.
+#[derive(Component)]
+pub struct PendingActivation;
+
+/// Switches a crystal from inactive to active, turning it solid once the player isn't in the way.
+fn queue_crystal_activation(
+    commands: &mut Commands,
+    crystal_entity: Entity,
+    crystal: &mut Crystal,
+) {
+    commands.entity(crystal_entity).insert(PendingActivation);
+    crystal.active = true;
+}
 
-/// Switches a crystal from inactive to active. Calling this on an already active crystal will
-/// result in weird behavior.
+/// Switches a crystal from active to inactive, cancelling its activation if it hasn't turned solid
+/// yet.
+fn set_crystal_inactive(
+    commands: &mut Commands,
+    crystal_entity: Entity,
+    crystal: &mut Crystal,
+    crystal_index: &mut TileTextureIndex,
+    pending: bool,
+) {
+    if pending {
+        commands
+            .entity(crystal_entity)
+            .remove::<PendingActivation>();
+    } else {
+        deactivate_crystal(commands, crystal_entity, crystal, crystal_index);
+    }
+    crystal.active = false;
+}
+
+/// Turns an activated crystal solid. Calling this on a crystal that is already solid will result
+/// in weird behavior.
 fn activate_crystal(
     commands: &mut Commands,
     crystal_entity: Entity,
+    crystal: &Crystal,
     crystal_index: &mut TileTextureIndex,
 ) {
     commands
         .entity(crystal_entity)
-        .insert(Collider::cuboid(4.0, 4.0));
-    crystal_index.0 -= CRYSTAL_INDEX_OFFSET;
+        .insert(Collider::cuboid(CRYSTAL_HALF_SIZE, CRYSTAL_HALF_SIZE))
+        .insert(CrystalAnimation::new(
+            &GROW_FRAMES,
+            crystal.active_index,
+            true,
+        ))
+        .remove::<PendingActivation>();
+    crystal_index.0 = crystal.active_index;
 }
 
 /// Switches a crystal from active to inactive. Calling this on an already inactive crystal will
@@ -256,28 +528,80 @@ fn activate_crystal(
 fn deactivate_crystal(
     commands: &mut Commands,
     crystal_entity: Entity,
+    crystal: &Crystal,
     crystal_index: &mut TileTextureIndex,
 ) {
-    commands.entity(crystal_entity).remove::<Collider>();
-    crystal_index.0 += CRYSTAL_INDEX_OFFSET;
+    commands
+        .entity(crystal_entity)
+        .remove::<Collider>()
+        .insert(CrystalAnimation::new(
+            &SHATTER_FRAMES,
+            crystal.active_index,
+            false,
+        ));
+    crystal_index.0 = crystal.active_index + CRYSTAL_INDEX_OFFSET;
 }
 
-/// [`System`] that listens to [`LevelSwitchEvent`]s to ensure that [`Crystal`] states are reset
-/// when switching between rooms.
+/// [`System`] that listens to [`ResetLevel`] events to ensure that [`Crystal`] states are reset
+/// when switching between rooms. When respawning in a level whose active [`Checkpoint`] snapshots
+/// crystals, the crystals in that level are restored to the snapshot instead. Crystals in
+/// [`WorldCrystalLinks`] follow the rules described there.
+///
+/// [`Checkpoint`]: super::checkpoint::Checkpoint
 pub fn reset_crystals(
     mut commands: Commands,
-    mut q_crystals: Query<(Entity, &mut Crystal, &mut TileTextureIndex)>,
+    mut q_crystals: Query<(
+        Entity,
+        &mut Crystal,
+        &mut TileTextureIndex,
+        &GridCoords,
+        Has<PendingActivation>,
+        Has<QueuedToggle>,
+    )>,
+    mut ev_reset_level: EventReader<ResetLevel>,
+    crystal_cache: Res<CrystalCache>,
+    current_level: Res<CurrentLevel>,
+    active_checkpoints: Res<ActiveCheckpoints>,
+    mut links: ResMut<WorldCrystalLinks>,
 ) {
-    for (entity, mut crystal, mut index) in q_crystals.iter_mut() {
-        if crystal.active == crystal.init_active {
+    let respawning = ev_reset_level.read().any(|ev| *ev == ResetLevel::Respawn);
+    let active_checkpoint = active_checkpoints
+        .get(&current_level.level_iid)
+        .filter(|_| respawning);
+    let snapshot = active_checkpoint.and_then(|checkpoint| checkpoint.crystals.as_ref());
+    if respawning {
+        links.toggled = active_checkpoint
+            .and_then(|checkpoint| checkpoint.world_crystal_links.clone())
+            .unwrap_or_else(|| links.on_entry.clone());
+    } else {
+        links.on_entry = links.toggled.clone();
+    }
+    let current_level_crystals: EntityHashSet = crystal_cache
+        .levels
+        .get(&current_level.level_iid)
+        .into_iter()
+        .flat_map(|color_map| color_map.values().flatten().copied())
+        .collect();
+
+    for (entity, mut crystal, mut index, coords, pending, queued) in q_crystals.iter_mut() {
+        if queued {
+            commands.entity(entity).remove::<QueuedToggle>();
+        }
+        let target_active = match snapshot {
+            _ if crystal.linked => crystal.init_active != links.is_toggled(crystal.color.id),
+            Some(snapshot) if current_level_crystals.contains(&entity) => {
+                snapshot.get(coords).copied().unwrap_or(crystal.init_active)
+            }
+            _ => crystal.init_active,
+        };
+        if crystal.active == target_active {
             continue;
         }
-        if crystal.init_active {
-            activate_crystal(&mut commands, entity, &mut index);
-            crystal.active = true;
+        // activations go through `solidify_crystals` so the player can't respawn inside of a crystal
+        if target_active {
+            queue_crystal_activation(&mut commands, entity, &mut crystal);
         } else {
-            deactivate_crystal(&mut commands, entity, &mut index);
-            crystal.active = false;
+            set_crystal_inactive(&mut commands, entity, &mut crystal, &mut index, pending);
         }
     }
 }
@@ -286,16 +610,56 @@ pub fn reset_crystals(
 #[derive(Event)]
 pub struct CrystalToggleEvent {
     pub color: CrystalColor,
+    /// Where the toggle came from, e.g. the button that was hit. Crystals toggle in a wave
+    /// spreading outwards from here.
+    pub origin: Vec2,
+}
+
+/// How long the toggle of a crystal is delayed for every pixel it is away from the
+/// [`CrystalToggleEvent`]'s origin.
+const CRYSTAL_STAGGER_SECS_PER_PIXEL: f32 = 0.002;
+/// The longest a crystal toggle can be delayed for, so that faraway crystals don't lag behind.
+const MAX_CRYSTAL_STAGGER_SECS: f32 = 0.4;
+
+/// [`Component`] for crystals that will be toggled once the timer finishes, staggering the toggle
+/// of a group of crystals.
+#[derive(Component)]
+pub struct QueuedToggle {
+    timer: Timer,
+    /// Whether the crystal is in the [`CurrentLevel`], so that it makes a sound and flashes when
+    /// it toggles. Crystals in other levels are toggled through [`WorldCrystalLinks`].
+    local: bool,
+}
+
+/// [`Resource`] holding the sounds crystals make when they toggle.
+#[derive(Resource)]
+struct CrystalSfx {
+    grow: Handle<AudioSource>,
+    shatter: Handle<AudioSource>,
 }
 
-/// [`System`] that listens to when [`Crystal`]s are activated or deactivated, updating the
-/// [`Sprite`] and adding/removing [`FixedEntityBundle`] of the [`Entity`].
+impl FromWorld for CrystalSfx {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        CrystalSfx {
+            grow: asset_server.load("sfx/crystal/grow.wav"),
+            shatter: asset_server.load("sfx/crystal/shatter.wav"),
+        }
+    }
+}
+
+/// [`System`] that listens for [`CrystalToggleEvent`]s, queuing a toggle for each crystal of the
+/// color that is delayed by its distance from the origin of the event. Toggling a crystal that is
+/// already queued cancels the queued toggle instead. If the color is linked in the
+/// [`CurrentLevel`], the crystals of every level linking it are toggled, see
+/// [`WorldCrystalLinks`].
 pub fn on_crystal_changed(
     mut commands: Commands,
-    mut q_crystal: Query<(&mut Crystal, &mut TileTextureIndex)>,
+    q_crystal: Query<(Entity, &Crystal, &GlobalTransform, Has<QueuedToggle>)>,
     mut crystal_toggle_ev: EventReader<CrystalToggleEvent>,
     crystal_cache: Res<CrystalCache>,
     current_level: Res<CurrentLevel>,
+    mut links: ResMut<WorldCrystalLinks>,
 ) {
     if crystal_toggle_ev.is_empty() {
         return;
@@ -304,22 +668,338 @@ pub fn on_crystal_changed(
         return;
     };
 
-    for CrystalToggleEvent { color } in crystal_toggle_ev.read() {
-        let Some(crystals) = color_map.get(color) else {
-            continue;
+    for CrystalToggleEvent { color, origin } in crystal_toggle_ev.read() {
+        let local_crystals = color_map.get(color).map(Vec::as_slice).unwrap_or_default();
+        let crystals: Vec<Entity> = if links.is_linked(&current_level.level_iid, *color) {
+            let toggled = links.toggled.entry(color.id).or_insert(false);
+            *toggled = !*toggled;
+            q_crystal
+                .iter()
+                .filter(|(_, crystal, _, _)| crystal.linked && crystal.color == *color)
+                .map(|(entity, _, _, _)| entity)
+                .collect()
+        } else {
+            local_crystals.to_vec()
         };
+
         for crystal_entity in crystals.iter() {
-            let Ok((mut crystal, mut index)) = q_crystal.get_mut(*crystal_entity) else {
+            let Ok((_, _, transform, queued)) = q_crystal.get(*crystal_entity) else {
                 continue;
             };
 
+            if queued {
+                commands.entity(*crystal_entity).remove::<QueuedToggle>();
+                continue;
+            }
+
+            let pos = transform.translation().truncate();
+            let delay = (pos.distance(*origin) * CRYSTAL_STAGGER_SECS_PER_PIXEL)
+                .min(MAX_CRYSTAL_STAGGER_SECS);
+            commands.entity(*crystal_entity).insert(QueuedToggle {
+                timer: Timer::from_seconds(delay, TimerMode::Once),
+                local: local_crystals.contains(crystal_entity),
+            });
+        }
+    }
+}
+
+/// [`System`] that toggles crystals once their [`QueuedToggle`] finishes. Crystals in the
+/// [`CurrentLevel`] flash as they toggle, and the crystals that grow or shatter on the same frame
+/// play one positional sound together, so that the toggle can be followed as it spreads.
+fn apply_queued_toggles(
+    mut commands: Commands,
+    mut q_crystal: Query<(
+        Entity,
+        &mut Crystal,
+        &mut TileTextureIndex,
+        &mut QueuedToggle,
+        &GlobalTransform,
+        Has<PendingActivation>,
+    )>,
+    crystal_sfx: Res<CrystalSfx>,
+    time: Res<Time>,
+) {
+    let mut growing = Vec::new();
+    let mut shattering = Vec::new();
+    for (entity, mut crystal, mut index, mut queued, transform, pending) in q_crystal.iter_mut() {
+        queued.timer.tick(time.delta());
+        if !queued.timer.finished() {
+            continue;
+        }
+        commands.entity(entity).remove::<QueuedToggle>();
+
+        if queued.local {
+            let pos = transform.translation();
             if crystal.active {
-                deactivate_crystal(&mut commands, *crystal_entity, &mut index);
-                crystal.active = false;
+                shattering.push(pos.truncate());
             } else {
-                activate_crystal(&mut commands, *crystal_entity, &mut index);
-                crystal.active = true;
+                growing.push(pos.truncate());
             }
+            commands.spawn((
+                CrystalFlash(Timer::from_seconds(CRYSTAL_FLASH_SECS, TimerMode::Once)),
+                Sprite::from_color(
+                    Color::from(crystal.color.color).with_alpha(CRYSTAL_FLASH_ALPHA),
+                    Vec2::splat(CRYSTAL_FLASH_SIZE),
+                ),
+                // draw the flash over the crystal
+                Transform::from_translation(pos + Vec3::Z),
+            ));
+        }
+
+        if crystal.active {
+            set_crystal_inactive(&mut commands, entity, &mut crystal, &mut index, pending);
+        } else {
+            queue_crystal_activation(&mut commands, entity, &mut crystal);
+        }
+    }
+
+    for (positions, sfx) in [
+        (growing, &crystal_sfx.grow),
+        (shattering, &crystal_sfx.shatter),
+    ] {
+        if positions.is_empty() {
+            continue;
+        }
+        let center = positions.iter().sum::<Vec2>() / positions.len() as f32;
+        commands.spawn((
+            AudioPlayer::new(sfx.clone()),
+            PlaybackSettings {
+                volume: Volume::new(CRYSTAL_SFX_VOLUME),
+                spatial: true,
+                spatial_scale: Some(SpatialScale::new_2d(CRYSTAL_SFX_SPATIAL_SCALE)),
+                ..PlaybackSettings::DESPAWN
+            },
+            Transform::from_translation(center.extend(0.0)),
+        ));
+    }
+}
+
+const CRYSTAL_SFX_VOLUME: f32 = 0.6;
+/// Converts pixels to the units used for positional audio, so that crystal sounds only fade out
+/// once they are a few screens away.
+const CRYSTAL_SFX_SPATIAL_SCALE: f32 = 1.0 / 64.0;
+const CRYSTAL_FLASH_SECS: f32 = 0.3;
+const CRYSTAL_FLASH_ALPHA: f32 = 0.6;
+/// The size of the flash over a toggling crystal, a bit bigger than the crystal.
+const CRYSTAL_FLASH_SIZE: f32 = 12.0;
+/// How long each frame of a [`CrystalAnimation`] is shown for.
+const CRYSTAL_FRAME_SECS: f32 = 0.05;
+
+/// The offset between the crystal tiles in the tileset and each frame of their grow and shatter
+/// sequences, which are stored below the original 16 rows of 26 tiles, one copy per frame. The
+/// shatter frames sit [`CRYSTAL_INDEX_OFFSET`] tiles to the right of the grow frames, in the same
+/// place as the inactive tiles.
+const CRYSTAL_FRAME_OFFSET: u32 = 16 * 26;
+
+/// Crystals form outward from their center, with a bright edge where they are growing.
+static GROW_FRAMES: [u32; 3] = [
+    CRYSTAL_FRAME_OFFSET,
+    2 * CRYSTAL_FRAME_OFFSET,
+    3 * CRYSTAL_FRAME_OFFSET,
+];
+
+/// Crystals crack, then break apart chunk by chunk.
+static SHATTER_FRAMES: [u32; 3] = [
+    CRYSTAL_INDEX_OFFSET + CRYSTAL_FRAME_OFFSET,
+    CRYSTAL_INDEX_OFFSET + 2 * CRYSTAL_FRAME_OFFSET,
+    CRYSTAL_INDEX_OFFSET + 3 * CRYSTAL_FRAME_OFFSET,
+];
+
+/// [`Component`] that steps a crystal's [`TileTextureIndex`] through its grow or shatter frames,
+/// before settling on the tile for its new state.
+#[derive(Component)]
+pub struct CrystalAnimation {
+    /// Offsets from the crystal's active tile to each frame
+    frames: &'static [u32],
+    frame: usize,
+    timer: Timer,
+    /// The [`TileTextureIndex`] of the crystal's active tile
+    active_index: u32,
+    /// Whether the crystal ends up active
+    activating: bool,
+}
+
+impl CrystalAnimation {
+    fn new(frames: &'static [u32], active_index: u32, activating: bool) -> Self {
+        CrystalAnimation {
+            frames,
+            frame: 0,
+            timer: Timer::from_seconds(CRYSTAL_FRAME_SECS, TimerMode::Repeating),
+            active_index,
+            activating,
+        }
+    }
+
+    fn final_index(&self) -> u32 {
+        if self.activating {
+            self.active_index
+        } else {
+            self.active_index + CRYSTAL_INDEX_OFFSET
         }
     }
 }
+
+/// [`System`] that plays [`CrystalAnimation`]s, removing them once they are done.
+fn animate_crystals(
+    mut commands: Commands,
+    mut q_crystals: Query<(Entity, &mut CrystalAnimation, &mut TileTextureIndex)>,
+    time: Res<Time>,
+) {
+    for (entity, mut animation, mut index) in q_crystals.iter_mut() {
+        animation.timer.tick(time.delta());
+        animation.frame += animation.timer.times_finished_this_tick() as usize;
+
+        let Some(offset) = animation.frames.get(animation.frame) else {
+            index.0 = animation.final_index();
+            commands.entity(entity).remove::<CrystalAnimation>();
+            continue;
+        };
+        index.0 = animation.active_index + offset;
+    }
+}
+
+/// [`Component`] for the brief flash over crystals as they toggle. This is a sprite rather than a
+/// [`PointLighting`](crate::lighting::light::PointLighting), since only a few lights can be drawn
+/// at once and a toggle can flash many crystals.
+#[derive(Component)]
+struct CrystalFlash(Timer);
+
+fn fade_crystal_flashes(
+    mut commands: Commands,
+    mut q_flashes: Query<(Entity, &mut CrystalFlash, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut sprite) in q_flashes.iter_mut() {
+        flash.0.tick(time.delta());
+        if flash.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        sprite
+            .color
+            .set_alpha(CRYSTAL_FLASH_ALPHA * flash.0.fraction_remaining());
+    }
+}
+
+/// Whether a player [`Collider`] at `player_pos` overlaps a crystal at `crystal_pos`.
+fn overlaps_crystal(player_pos: Vec2, player_half_size: Vec2, crystal_pos: Vec2) -> bool {
+    let gap = (player_pos - crystal_pos).abs() - player_half_size - Vec2::splat(CRYSTAL_HALF_SIZE);
+    gap.x < 0.0 && gap.y < 0.0
+}
+
+/// [`System`] that turns crystals with [`PendingActivation`] solid. If the player is inside of a
+/// crystal, the [`CrushBehavior`] of its color decides whether the player is killed, pushed out
+/// of the crystal, or whether the crystal waits for the player to leave.
+pub fn solidify_crystals(
+    mut commands: Commands,
+    mut q_pending: Query<
+        (Entity, &Crystal, &mut TileTextureIndex, &GlobalTransform),
+        With<PendingActivation>,
+    >,
+    mut q_player: Query<(Entity, &mut Transform, &Collider), With<PlayerMarker>>,
+    rapier_context: ReadDefaultRapierContext,
+    mut ev_kill_player: EventWriter<KillPlayerEvent>,
+    config: Res<Config>,
+) {
+    if q_pending.is_empty() {
+        return;
+    }
+    let Ok((player, mut player_transform, player_collider)) = q_player.get_single_mut() else {
+        for (entity, crystal, mut index, _) in q_pending.iter_mut() {
+            activate_crystal(&mut commands, entity, crystal, &mut index);
+        }
+        return;
+    };
+    let player_pos = player_transform.translation.truncate();
+    let player_half_size = match player_collider.as_cuboid() {
+        Some(cuboid) => cuboid.half_extents(),
+        None => Vec2::ZERO,
+    };
+
+    let mut crushing = Vec::new();
+    for (entity, crystal, mut index, transform) in q_pending.iter_mut() {
+        let crystal_pos = transform.translation().truncate();
+        if !overlaps_crystal(player_pos, player_half_size, crystal_pos) {
+            activate_crystal(&mut commands, entity, crystal, &mut index);
+            continue;
+        }
+        match config.crush_config.behavior(crystal.color.color) {
+            CrushBehavior::Defer => {}
+            behavior => crushing.push((entity, crystal_pos, behavior)),
+        }
+    }
+    if crushing.is_empty() {
+        return;
+    }
+
+    let free_spot = crushing
+        .iter()
+        .all(|(_, _, behavior)| *behavior == CrushBehavior::PushOut)
+        .then(|| {
+            find_free_spot(
+                player_pos,
+                player_half_size,
+                &crushing.iter().map(|(_, pos, _)| *pos).collect::<Vec<_>>(),
+                player,
+                player_collider,
+                &rapier_context,
+            )
+        })
+        .flatten();
+    match free_spot {
+        Some(free_spot) => {
+            player_transform.translation.x = free_spot.x;
+            player_transform.translation.y = free_spot.y;
+        }
+        None => {
+            ev_kill_player.send(KillPlayerEvent {
+                cause: DeathCause::Crushed,
+            });
+        }
+    }
+    for (entity, _, _) in crushing {
+        if let Ok((_, crystal, mut index, _)) = q_pending.get_mut(entity) {
+            activate_crystal(&mut commands, entity, crystal, &mut index);
+        }
+    }
+}
+
+/// Finds the closest position to `player_pos` where the player isn't inside of terrain or any of
+/// the `crystals` about to turn solid, preferring to push the player upwards.
+fn find_free_spot(
+    player_pos: Vec2,
+    player_half_size: Vec2,
+    crystals: &[Vec2],
+    player: Entity,
+    player_collider: &Collider,
+    rapier_context: &RapierContext,
+) -> Option<Vec2> {
+    const DIRECTIONS: [Vec2; 8] = [
+        Vec2::Y,
+        Vec2::NEG_X,
+        Vec2::X,
+        Vec2::NEG_Y,
+        Vec2::new(-1.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+    ];
+    let filter = QueryFilter::new()
+        .groups(CollisionGroups::new(
+            GroupLabel::PLAYER_COLLIDER,
+            GroupLabel::TERRAIN,
+        ))
+        .exclude_collider(player)
+        .exclude_sensors();
+
+    (1..=MAX_PUSH_OUT_DISTANCE)
+        .flat_map(|distance| DIRECTIONS.map(|direction| player_pos + direction * distance as f32))
+        .find(|&candidate| {
+            crystals
+                .iter()
+                .all(|&crystal| !overlaps_crystal(candidate, player_half_size, crystal))
+                && rapier_context
+                    .intersection_with_shape(candidate, 0.0, player_collider, filter)
+                    .is_none()
+        })
+}
@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::EntityRef, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    player::{movement::PLAYER_HALF_HEIGHT, PlayerHurtMarker, PlayerMarker},
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::{
+    complete_left_level, error::LevelDataCheck, level_world_box, switch_level, CompletedLevels,
+    CurrentLevel, LevelSystems, PendingTeleport,
+};
+
+/// [`Plugin`] for [`Door`]s and warps, which move the player to another entity anywhere in the
+/// world. This lets levels be connected without being next to each other in Ldtk.
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<DoorBundle>("Door")
+            .register_ldtk_entity::<DoorBundle>("Warp")
+            .add_systems(
+                Update,
+                enter_doors
+                    .before(switch_level)
+                    .in_set(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// How the player goes through a [`Door`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorKind {
+    /// Entered by pressing W while standing in front of it
+    #[default]
+    Door,
+    /// Entered as soon as the player touches it
+    Warp,
+}
+
+/// [`Component`] for doors and warps, holding the entity they lead to, set in Ldtk.
+#[derive(Default, Component)]
+pub struct Door {
+    kind: DoorKind,
+    /// Missing destinations are reported by the door's `LevelDataCheck`
+    destination: Option<EntityRef>,
+}
+
+impl From<&EntityInstance> for Door {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let kind = match entity_instance.identifier.as_str() {
+            "Warp" => DoorKind::Warp,
+            _ => DoorKind::Door,
+        };
+        Door {
+            kind,
+            destination: entity_instance
+                .get_entity_ref_field("destination")
+                .ok()
+                .cloned(),
+        }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn doors and warps.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[from_entity_instance]
+    door: Door,
+    #[from_entity_instance]
+    sensor: DoorSensorBundle,
+    #[from_entity_instance]
+    check: LevelDataCheck,
+}
+
+/// [`Bundle`] for the [`Sensor`] that the player has to be touching to go through the door.
+#[derive(Default, Bundle)]
+struct DoorSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+    collision_groups: CollisionGroups,
+}
+
+impl From<&EntityInstance> for DoorSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        DoorSensorBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            sensor: Sensor,
+            collision_groups: CollisionGroups::new(GroupLabel::TRIGGER, GroupLabel::PLAYER_SENSOR),
+        }
+    }
+}
+
+/// Where the player comes out of a door leading to `entity`, which is the bottom center of the
+/// entity raised by the player's half height.
+fn door_destination_point(entity: &EntityInstance) -> Option<Vec2> {
+    let (world_x, world_y) = (entity.world_x?, entity.world_y?);
+    let bottom = world_y as f32 + (1.0 - entity.pivot.y) * entity.height as f32;
+    Some(Vec2::new(
+        world_x as f32 + (0.5 - entity.pivot.x) * entity.width as f32,
+        // add small height so Lyra is not stuck into the floor
+        -bottom + PLAYER_HALF_HEIGHT + 1.0,
+    ))
+}
+
+/// [`System`] that moves the player to the destination of the door they went through. If the
+/// destination is in another level, the [`CurrentLevel`] is switched to it right away, the same
+/// way as picking a level in the level select, and its entity is filled in by [`switch_level`]
+/// once the level has spawned.
+///
+/// Warps only fire once the player has stepped off every warp since their last teleport, so that
+/// arriving on a warp doesn't send them straight back.
+#[allow(clippy::too_many_arguments)]
+fn enter_doors(
+    rapier_context: ReadDefaultRapierContext,
+    q_player_sensor: Query<Entity, With<PlayerHurtMarker>>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    q_doors: Query<(Entity, &Door)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut completed_levels: ResMut<CompletedLevels>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+    mut on_warp: Local<bool>,
+) {
+    let Ok(player_sensor) = q_player_sensor.get_single() else {
+        return;
+    };
+    let touching: Vec<&Door> = q_doors
+        .iter()
+        .filter(|(entity, _)| {
+            rapier_context.intersection_pair(player_sensor, *entity) == Some(true)
+        })
+        .map(|(_, door)| door)
+        .collect();
+
+    let touching_warp = touching.iter().any(|door| door.kind == DoorKind::Warp);
+    let was_on_warp = *on_warp;
+    *on_warp = touching_warp || pending_teleport.0.is_some();
+    if pending_teleport.0.is_some() {
+        return;
+    }
+
+    let Some(door) = touching.into_iter().find(|door| match door.kind {
+        DoorKind::Door => keys.just_pressed(KeyCode::KeyW),
+        DoorKind::Warp => !was_on_warp,
+    }) else {
+        return;
+    };
+    let Some(destination) = &door.destination else {
+        return;
+    };
+    let Ok(project_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Some(project) = ldtk_project_assets.get(project_handle) else {
+        return;
+    };
+    let Some((level, point)) = project
+        .get_raw_level_by_iid(&destination.level_iid)
+        .and_then(|level| {
+            let entity = level
+                .layer_instances
+                .iter()
+                .flatten()
+                .flat_map(|layer| layer.entity_instances.iter())
+                .find(|entity| entity.iid == destination.entity_iid)?;
+            Some((level, door_destination_point(entity)?))
+        })
+    else {
+        warn!(
+            "Door destination {} in level {} does not exist",
+            destination.entity_iid, destination.level_iid
+        );
+        return;
+    };
+    let Ok(mut transform) = q_player.get_single_mut() else {
+        return;
+    };
+
+    // otherwise `switch_level` would see the player outside of the level they were in, and
+    // playlists would move on if it is the last level of the project
+    if current_level.level_iid.get() != &level.iid {
+        complete_left_level(project, &current_level.level_iid, &mut completed_levels);
+        *current_level = CurrentLevel {
+            level_iid: LevelIid::new(level.iid.clone()),
+            level_entity: None,
+            world_box: level_world_box(level),
+        };
+        ev_reset_level.send(ResetLevel::Switching);
+    }
+    *level_selection = LevelSelection::iid(destination.level_iid.clone());
+    transform.translation.x = point.x;
+    transform.translation.y = point.y;
+    pending_teleport.0 = Some(point);
+    *on_warp = true;
+}
@@ -3,7 +3,10 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
-    player::{movement::PlayerMovement, PlayerMarker},
+    player::{
+        movement::{PlayerMovement, PLAYER_CROUCH_HALF_HEIGHT, PLAYER_HALF_HEIGHT},
+        PlayerMarker,
+    },
     shared::GroupLabel,
 };
 
@@ -34,9 +37,8 @@ pub fn set_semisolid(
     let Ok((movement, player)) = q_player.get_single() else {
         return;
     };
-    const PLAYER_HALF_HEIGHT: f32 = 9.0;
     let cutoff_height = if movement.crouching {
-        PLAYER_HALF_HEIGHT / 2.0
+        PLAYER_CROUCH_HALF_HEIGHT
     } else {
         PLAYER_HALF_HEIGHT
     };
@@ -55,9 +57,24 @@ pub fn set_semisolid(
     }
 }
 
+/// The damage dealt by spikes with [`Difficulty::Health`](crate::config::Difficulty::Health).
+const SPIKE_DAMAGE: u32 = 1;
+
 /// Component for things that hurt
-#[derive(Default, Component)]
-pub struct HurtMarker;
+#[derive(Component)]
+pub struct HurtMarker {
+    /// How much health the player loses when touching this, with
+    /// [`Difficulty::Health`](crate::config::Difficulty::Health)
+    pub damage: u32,
+}
+
+impl Default for HurtMarker {
+    fn default() -> Self {
+        HurtMarker {
+            damage: SPIKE_DAMAGE,
+        }
+    }
+}
 
 /// Component for spikes
 #[derive(Default, Component)]
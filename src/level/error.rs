@@ -0,0 +1,331 @@
+use std::{collections::HashSet, fmt};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{prelude::*, systems::process_ldtk_levels};
+
+use crate::light::LightColor;
+
+use super::{ldtk_data::cell_grid, registry::registered_int_grid_values, LevelSystems};
+
+/// How long the on-screen list of [`LevelDataError`]s stays up after the last error.
+#[cfg(debug_assertions)]
+const LEVEL_DATA_WARNING_SECS: f32 = 10.0;
+/// The number of most recent [`LevelDataError`]s listed on screen.
+#[cfg(debug_assertions)]
+const MAX_SHOWN_LEVEL_DATA_ERRORS: usize = 5;
+
+/// [`Plugin`] that reports malformed level data with [`LevelDataError`]s instead of crashing the
+/// game. Entities built from bad data are skipped, and in debug builds the errors are listed on
+/// screen so they aren't missed during a playtest.
+pub struct LevelDataErrorPlugin;
+
+impl Plugin for LevelDataErrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelDataError>()
+            .add_systems(
+                PreUpdate,
+                (skip_invalid_level_data, report_unknown_int_grid_values)
+                    .after(process_ldtk_levels)
+                    .before(LevelSystems::Processing),
+            )
+            .add_systems(Update, log_level_data_errors);
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Startup, spawn_level_data_warning)
+            .add_systems(Update, show_level_data_warning);
+    }
+}
+
+/// What is wrong with a piece of level data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelDataErrorKind {
+    /// An enum field holds a value that isn't a [`LightColor`]
+    UnknownLightColor(String),
+    /// An entity is missing a field, or the field has the wrong type
+    MissingField(&'static str),
+    /// An int grid cell has a value that the game doesn't handle
+    UnknownIntGridValue(i32),
+    /// An entity has no world position, which happens if the project doesn't use the Free world
+    /// layout
+    MissingWorldPosition,
+    /// A level has no start flag or active checkpoint for the player to respawn at
+    NoRespawnPoint,
+}
+
+impl fmt::Display for LevelDataErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelDataErrorKind::UnknownLightColor(color) => {
+                write!(f, "{color} is not a light color")
+            }
+            LevelDataErrorKind::MissingField(field) => write!(f, "missing the {field} field"),
+            LevelDataErrorKind::UnknownIntGridValue(value) => {
+                write!(f, "unknown int grid value {value}")
+            }
+            LevelDataErrorKind::MissingWorldPosition => {
+                write!(
+                    f,
+                    "no world position, Lightborne uses the Free world layout"
+                )
+            }
+            LevelDataErrorKind::NoRespawnPoint => {
+                write!(f, "no start flag or checkpoint to respawn at")
+            }
+        }
+    }
+}
+
+/// [`Event`] sent when malformed level data is found, along with where it was found.
+#[derive(Event, Debug, Clone)]
+pub struct LevelDataError {
+    pub kind: LevelDataErrorKind,
+    pub level_iid: Option<LevelIid>,
+    pub entity_iid: Option<EntityIid>,
+    pub grid_coords: Option<GridCoords>,
+}
+
+impl fmt::Display for LevelDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(level_iid) = &self.level_iid {
+            write!(f, ", in level {}", level_iid.get())?;
+        }
+        if let Some(entity_iid) = &self.entity_iid {
+            write!(f, ", on entity {}", entity_iid.get())?;
+        }
+        if let Some(coords) = &self.grid_coords {
+            write!(f, ", at ({}, {})", coords.x, coords.y)?;
+        }
+        Ok(())
+    }
+}
+
+/// [`Component`] added by Ldtk bundles that can be built from malformed data, holding what was
+/// wrong with the data, if anything. Bundles with bad data are built with placeholder values, and
+/// are despawned by [`skip_invalid_level_data`] before anything else processes them.
+#[derive(Default, Component, Debug)]
+pub struct LevelDataCheck(Option<LevelDataErrorKind>);
+
+impl From<&EntityInstance> for LevelDataCheck {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        LevelDataCheck(check_entity_instance(entity_instance).err())
+    }
+}
+
+/// Checks that an entity has the fields the game expects. The level validator runs this too, so
+/// that it reports the same problems as the game.
+pub fn check_entity_instance(entity_instance: &EntityInstance) -> Result<(), LevelDataErrorKind> {
+    if entity_instance.world_x.is_none() || entity_instance.world_y.is_none() {
+        return Err(LevelDataErrorKind::MissingWorldPosition);
+    }
+    match entity_instance.identifier.as_str() {
+        "Button" => {
+            LightColor::try_from(
+                entity_instance
+                    .get_enum_field("light_color")
+                    .map_err(missing_field("light_color"))?,
+            )?;
+            entity_instance
+                .get_int_field("id")
+                .map_err(missing_field("id"))?;
+        }
+        "Checkpoint" => {
+            entity_instance
+                .get_bool_field("snapshot_crystals")
+                .map_err(missing_field("snapshot_crystals"))?;
+        }
+        "Strands" => {
+            entity_instance
+                .get_string_field("strand_preset")
+                .map_err(missing_field("strand_preset"))?;
+        }
+        "WindZone" => {
+            for field in ["force_x", "force_y"] {
+                entity_instance
+                    .get_float_field(field)
+                    .map_err(missing_field(field))?;
+            }
+        }
+        "Door" | "Warp" => {
+            entity_instance
+                .get_entity_ref_field("destination")
+                .map_err(missing_field("destination"))?;
+        }
+        "Carryable" => {
+            entity_instance
+                .get_bool_field("reflective")
+                .map_err(missing_field("reflective"))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn missing_field<E>(field: &'static str) -> impl FnOnce(E) -> LevelDataErrorKind {
+    move |_| LevelDataErrorKind::MissingField(field)
+}
+
+/// [`System`] that sends a [`LevelDataError`] for every entity with a failed [`LevelDataCheck`]
+/// and despawns it, so that the rest of the game never sees it.
+fn skip_invalid_level_data(
+    mut commands: Commands,
+    q_checks: Query<
+        (
+            Entity,
+            &LevelDataCheck,
+            &Parent,
+            Option<&EntityIid>,
+            Option<&GridCoords>,
+        ),
+        Added<LevelDataCheck>,
+    >,
+    q_parent: Query<&Parent, Without<LevelDataCheck>>,
+    q_level_iid: Query<&LevelIid>,
+    mut ev_level_data_error: EventWriter<LevelDataError>,
+) {
+    for (entity, check, parent, entity_iid, grid_coords) in q_checks.iter() {
+        let Some(kind) = &check.0 else {
+            continue;
+        };
+        // entities are children of their layer, which is a child of the level
+        let level_iid = q_parent
+            .get(parent.get())
+            .ok()
+            .and_then(|layer_parent| q_level_iid.get(layer_parent.get()).ok())
+            .cloned();
+        ev_level_data_error.send(LevelDataError {
+            kind: kind.clone(),
+            level_iid,
+            entity_iid: entity_iid.cloned(),
+            grid_coords: grid_coords.copied(),
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// [`System`] that sends a [`LevelDataError`] for values on the int grid layers of newly spawned
+/// levels that have no bundle registered. Ldtk skips those cells without spawning anything, so
+/// this reads the layers of the level straight from the project.
+fn report_unknown_int_grid_values(
+    q_levels: Query<&LevelIid, Added<LevelIid>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut ev_level_data_error: EventWriter<LevelDataError>,
+) {
+    if q_levels.is_empty() {
+        return;
+    }
+    let Some(ldtk_project) = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+    else {
+        return;
+    };
+
+    for level_iid in q_levels.iter() {
+        let Some(layers) = ldtk_project
+            .get_raw_level_by_iid(level_iid.get())
+            .and_then(|level| level.layer_instances.as_ref())
+        else {
+            continue;
+        };
+        for layer in layers.iter() {
+            let registered = registered_int_grid_values(&layer.identifier);
+            if registered.is_empty() {
+                continue;
+            }
+            // a bad value is usually painted over many cells, so only report where it is first
+            let mut reported = HashSet::new();
+            for (i, &value) in layer.int_grid_csv.iter().enumerate() {
+                // 0 is an empty cell
+                if value == 0 || registered.contains(&value) || !reported.insert(value) {
+                    continue;
+                }
+                // Ldtk counts rows from the top, while grid coords count them from the bottom
+                let cell = cell_grid(layer, i);
+                ev_level_data_error.send(LevelDataError {
+                    kind: LevelDataErrorKind::UnknownIntGridValue(value),
+                    level_iid: Some(level_iid.clone()),
+                    entity_iid: None,
+                    grid_coords: Some(GridCoords::new(cell.x, layer.c_hei - 1 - cell.y)),
+                });
+            }
+        }
+    }
+}
+
+fn log_level_data_errors(mut ev_level_data_error: EventReader<LevelDataError>) {
+    for ev in ev_level_data_error.read() {
+        error!("Malformed level data: {ev}");
+    }
+}
+
+/// [`Component`] for the on-screen list of recent [`LevelDataError`]s in debug builds.
+#[cfg(debug_assertions)]
+#[derive(Component)]
+struct LevelDataWarning {
+    lines: Vec<String>,
+    /// Hides the list once it finishes
+    timer: Timer,
+}
+
+#[cfg(debug_assertions)]
+fn spawn_level_data_warning(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.3, 0.3)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        LevelDataWarning {
+            lines: Vec::new(),
+            timer: Timer::from_seconds(LEVEL_DATA_WARNING_SECS, TimerMode::Once),
+        },
+    ));
+}
+
+/// [`System`] that lists the most recent [`LevelDataError`]s on screen, hiding them once no new
+/// errors have come in for a while.
+#[cfg(debug_assertions)]
+fn show_level_data_warning(
+    mut q_warning: Query<(&mut LevelDataWarning, &mut Text, &mut Visibility)>,
+    mut ev_level_data_error: EventReader<LevelDataError>,
+    time: Res<Time>,
+) {
+    let Ok((mut warning, mut text, mut visibility)) = q_warning.get_single_mut() else {
+        return;
+    };
+
+    if !ev_level_data_error.is_empty() {
+        warning
+            .lines
+            .extend(ev_level_data_error.read().map(|ev| ev.to_string()));
+        let excess = warning
+            .lines
+            .len()
+            .saturating_sub(MAX_SHOWN_LEVEL_DATA_ERRORS);
+        warning.lines.drain(..excess);
+
+        text.0 = format!(
+            "Malformed level data, see the log for details:\n{}",
+            warning.lines.join("\n")
+        );
+        *visibility = Visibility::Visible;
+        warning.timer.reset();
+    }
+
+    warning.timer.tick(time.delta());
+    if warning.timer.just_finished() {
+        warning.lines.clear();
+        *visibility = Visibility::Hidden;
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    light::LightRaySource,
+    player::{DeathCause, KillPlayerEvent, PlayerHurtMarker},
+    shared::{GameState, GroupLabel, ResetLevel},
+};
+
+use super::{CompletedLevels, CurrentLevel, LevelSystems};
+
+/// [`Plugin`] for [`Exit`] zones and the [`LevelStats`] of the attempt at the current level. When
+/// the player walks into an exit, a [`LevelCompleted`] event is sent and the game switches to
+/// [`GameState::LevelSummary`] until the summary is dismissed.
+pub struct ExitPlugin;
+
+impl Plugin for ExitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStats>()
+            .add_event::<LevelCompleted>()
+            .register_ldtk_entity::<ExitBundle>("Exit")
+            .add_systems(
+                Update,
+                (count_beams_fired, tick_level_time, complete_levels)
+                    .chain()
+                    .in_set(LevelSystems::Simulation),
+            )
+            .add_systems(Update, reset_level_stats.run_if(on_event::<ResetLevel>))
+            .add_systems(
+                Update,
+                count_deaths
+                    .run_if(on_event::<KillPlayerEvent>)
+                    .run_if(in_state(GameState::Playing))
+                    .after(LevelSystems::Simulation),
+            );
+    }
+}
+
+/// [`Component`] for zones that complete the level they are in.
+#[derive(Default, Component)]
+pub struct Exit {
+    /// The center of the zone in world coordinates, used to tell whether it is in the
+    /// [`CurrentLevel`]
+    center: Vec2,
+}
+
+impl From<&EntityInstance> for Exit {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let center = Vec2::new(
+            entity_instance.world_x.unwrap_or_default() as f32
+                + (0.5 - entity_instance.pivot.x) * entity_instance.width as f32,
+            -(entity_instance.world_y.unwrap_or_default() as f32
+                + (0.5 - entity_instance.pivot.y) * entity_instance.height as f32),
+        );
+        Exit { center }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn exits.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct ExitBundle {
+    #[from_entity_instance]
+    exit: Exit,
+    #[from_entity_instance]
+    sensor: ExitSensorBundle,
+}
+
+/// [`Bundle`] for the [`Sensor`] that completes the level when the player touches it.
+#[derive(Default, Bundle)]
+struct ExitSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+    collision_groups: CollisionGroups,
+}
+
+impl From<&EntityInstance> for ExitSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        ExitSensorBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            sensor: Sensor,
+            collision_groups: CollisionGroups::new(GroupLabel::TRIGGER, GroupLabel::PLAYER_SENSOR),
+        }
+    }
+}
+
+/// [`Resource`] tracking the player's attempt at the [`CurrentLevel`], from when they entered it.
+/// Cleared on every [`ResetLevel::Switching`], but kept across respawns.
+#[derive(Default, Resource, Debug)]
+pub struct LevelStats {
+    /// Time spent playing the level, not counting pauses or menus
+    pub time: Duration,
+    pub deaths: u32,
+    pub beams_fired: u32,
+    /// Whether the level has already been completed during this attempt, so that standing in an
+    /// exit only completes it once
+    pub completed: bool,
+}
+
+/// [`Event`] sent when the player walks into an [`Exit`] of the [`CurrentLevel`], with their
+/// [`LevelStats`] for the level.
+#[derive(Event, Debug, Clone)]
+pub struct LevelCompleted {
+    pub level_iid: LevelIid,
+    pub time: Duration,
+    pub deaths: u32,
+    pub beams_fired: u32,
+}
+
+fn count_beams_fired(
+    q_light_sources: Query<(), Added<LightRaySource>>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    level_stats.beams_fired += q_light_sources.iter().count() as u32;
+}
+
+fn tick_level_time(time: Res<Time>, mut level_stats: ResMut<LevelStats>) {
+    level_stats.time += time.delta();
+}
+
+/// [`System`] that counts the deaths that start a death sequence, which happens at most once a
+/// frame. Restarting from the last checkpoint with R isn't counted as a death.
+fn count_deaths(
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    let deaths = ev_kill_player
+        .read()
+        .filter(|ev| ev.cause != DeathCause::Reset)
+        .count();
+    if deaths > 0 {
+        level_stats.deaths += 1;
+    }
+}
+
+fn reset_level_stats(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    if ev_reset_level.read().any(|ev| *ev == ResetLevel::Switching) {
+        *level_stats = LevelStats::default();
+    }
+}
+
+/// [`System`] that completes the [`CurrentLevel`] when the player touches one of its [`Exit`]s,
+/// recording it in [`CompletedLevels`] and opening the level summary.
+#[allow(clippy::too_many_arguments)]
+fn complete_levels(
+    rapier_context: ReadDefaultRapierContext,
+    q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_exits: Query<(Entity, &Exit)>,
+    current_level: Res<CurrentLevel>,
+    mut level_stats: ResMut<LevelStats>,
+    mut completed_levels: ResMut<CompletedLevels>,
+    mut ev_level_completed: EventWriter<LevelCompleted>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if level_stats.completed {
+        return;
+    }
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    let reached_exit = q_exits.iter().any(|(entity, exit)| {
+        current_level.world_box.contains(exit.center)
+            && rapier_context.intersection_pair(player, entity) == Some(true)
+    });
+    if !reached_exit {
+        return;
+    }
+
+    level_stats.completed = true;
+    completed_levels.0.insert(current_level.level_iid.clone());
+    ev_level_completed.send(LevelCompleted {
+        level_iid: current_level.level_iid.clone(),
+        time: level_stats.time,
+        deaths: level_stats.deaths,
+        beams_fired: level_stats.beams_fired,
+    });
+    next_state.set(GameState::LevelSummary);
+}
@@ -0,0 +1,128 @@
+//! Helpers for reading levels straight from an [`LdtkJson`](bevy_ecs_ldtk::ldtk::LdtkJson)
+//! project, without spawning them. Used by the validator and solver binaries, which need to see
+//! levels the same way the game does.
+
+use std::fmt;
+
+use bevy::math::{IVec2, Rect, Vec2};
+use bevy_ecs_ldtk::{
+    ldtk::{EntityInstance, LayerInstance},
+    prelude::{IntGridCell, LdtkFields},
+};
+
+use crate::light::LightColor;
+
+use super::crystal::{crystal_color, find_crystal_group, is_crystal_active};
+
+/// The crystals a button toggles, the same as [`CrystalColor`](super::crystal::CrystalColor) in
+/// the game.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CrystalKey {
+    pub color: LightColor,
+    pub id: CrystalKeyId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CrystalKeyId {
+    /// An ID painted on the Crystalmap layer or set on a button, 0 if there is none
+    Painted(i32),
+    /// The iid of a [`CrystalGroup`](super::crystal::CrystalGroup) entity
+    Group(String),
+}
+
+impl fmt::Display for CrystalKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.id {
+            CrystalKeyId::Painted(0) => write!(f, "{:?} crystals", self.color),
+            CrystalKeyId::Painted(id) => write!(f, "{:?} crystals with ID {}", self.color, id),
+            CrystalKeyId::Group(iid) => write!(f, "{:?} crystals in group {}", self.color, iid),
+        }
+    }
+}
+
+/// A crystal on the Terrain layer of a level.
+#[derive(Debug, Clone)]
+pub struct LdtkCrystal {
+    /// Grid coordinates with y pointing down, as in Ldtk
+    pub cell: IVec2,
+    pub active: bool,
+    pub key: CrystalKey,
+}
+
+/// Collects the crystals on a level's Terrain layer, giving white crystals the ID of the group
+/// they are in and falling back to the Crystalmap layer, in the same way as
+/// `init_crystal_cache_and_ids`.
+pub fn ldtk_crystals(
+    terrain: &LayerInstance,
+    crystalmap: Option<&LayerInstance>,
+    entities: &[EntityInstance],
+) -> Vec<LdtkCrystal> {
+    let groups: Vec<(Rect, &EntityInstance)> = entities
+        .iter()
+        .filter(|entity| entity.identifier == "CrystalGroup")
+        .map(|entity| {
+            let (min, max) = entity_bounds(entity);
+            (Rect::from_corners(min, max), entity)
+        })
+        .collect();
+
+    terrain
+        .int_grid_csv
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &value)| {
+            let cell = IntGridCell { value };
+            let (color, active) = (crystal_color(cell)?, is_crystal_active(cell)?);
+            let grid = cell_grid(terrain, i);
+            let center = (grid.as_vec2() + 0.5) * terrain.grid_size as f32;
+            // groups only hold white crystals
+            let group = find_crystal_group(groups.iter().copied(), center)
+                .filter(|_| color == LightColor::White);
+            let id = match group {
+                Some(group) => CrystalKeyId::Group(group.iid.clone()),
+                None => CrystalKeyId::Painted(
+                    crystalmap
+                        .and_then(|layer| layer.int_grid_csv.get(i))
+                        .copied()
+                        .unwrap_or(0),
+                ),
+            };
+            Some(LdtkCrystal {
+                cell: grid,
+                active,
+                key: CrystalKey { color, id },
+            })
+        })
+        .collect()
+}
+
+/// The crystals a Button entity toggles, or [`None`] if it is missing its `light_color` or `id`.
+/// Only white buttons use their `crystal_group`, as in `resolve_crystal_group_refs`.
+pub fn button_crystal_key(button: &EntityInstance) -> Option<CrystalKey> {
+    let color = LightColor::try_from(button.get_enum_field("light_color").ok()?).ok()?;
+    let id = match button.get_maybe_entity_ref_field("crystal_group") {
+        Ok(Some(group)) if color == LightColor::White => {
+            CrystalKeyId::Group(group.entity_iid.clone())
+        }
+        _ => CrystalKeyId::Painted(*button.get_int_field("id").ok()?),
+    };
+    Some(CrystalKey { color, id })
+}
+
+/// The grid coordinates of the `i`th cell of an int grid layer, with y pointing down as in Ldtk.
+pub fn cell_grid(layer: &LayerInstance, i: usize) -> IVec2 {
+    IVec2::new(i as i32 % layer.c_wid, i as i32 / layer.c_wid)
+}
+
+/// The top left and bottom right corners of an entity, in pixels relative to its level.
+pub fn entity_bounds(entity: &EntityInstance) -> (Vec2, Vec2) {
+    let size = Vec2::new(entity.width as f32, entity.height as f32);
+    let min = entity.px.as_vec2() - entity.pivot * size;
+    (min, min + size)
+}
+
+/// The center of an entity, in pixels relative to the top left of its level, with y pointing down.
+pub fn entity_center(entity: &EntityInstance) -> Vec2 {
+    let (min, max) = entity_bounds(entity);
+    min.midpoint(max)
+}
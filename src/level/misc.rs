@@ -1,8 +1,10 @@
 use bevy::prelude::*;
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
 
 use crate::light::sensor::LightSensorBundle;
 
+use super::{crystal::CrystalGroupRef, error::LevelDataCheck};
+
 /// [`Component`] to mark buttons in the level.
 #[derive(Default, Component)]
 pub struct ButtonMarker;
@@ -16,6 +18,10 @@ pub struct ButtonBundle {
     sprite_sheet: Sprite,
     #[from_entity_instance]
     light_sensor: LightSensorBundle,
+    #[from_entity_instance]
+    crystal_group: CrystalGroupRef,
+    #[from_entity_instance]
+    check: LevelDataCheck,
 }
 
 /// [`Component`] to mark start flags in the level. Used to query for when start flags are loaded
@@ -38,6 +44,8 @@ pub struct StartFlagBundle {
     marker: StartMarker,
     #[from_entity_instance]
     instance: EntityInstance,
+    #[from_entity_instance]
+    check: LevelDataCheck,
 }
 
 /// Initializes the start maker with the `level_iid`, which must be gotten with queries.
@@ -63,3 +71,23 @@ pub fn init_start_marker(
         });
     }
 }
+
+/// Finds the first entity with the `identifier` in a level's raw Ldtk data, which is available
+/// before the level has spawned.
+pub fn find_entity_instance<'a>(level: &'a Level, identifier: &str) -> Option<&'a EntityInstance> {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .find(|entity| entity.identifier == identifier)
+}
+
+/// Where the player should be placed to start a level, from the world position of its start flag
+/// in the level's raw Ldtk data.
+pub fn start_flag_position(level: &Level) -> Option<Vec2> {
+    let flag = find_entity_instance(level, "Start")?;
+    let (world_x, world_y) = flag.world_x.zip(flag.world_y)?;
+    // add small height so Lyra is not stuck into the floor
+    Some(Vec2::new(world_x as f32, -world_y as f32 + 3.0))
+}
@@ -1,20 +1,40 @@
+use std::collections::HashSet;
+
 use bevy::prelude::*;
-use bevy_ecs_ldtk::{prelude::*, systems::process_ldtk_levels};
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*, systems::process_ldtk_levels};
 
 use crate::{
-    player::{LdtkPlayerBundle, PlayerMarker},
+    player::{movement::PlayerMovement, LdtkPlayerBundle, PlayerMarker},
     shared::{GameState, ResetLevel},
 };
+use carryable::CarryablePlugin;
+use checkpoint::CheckpointPlugin;
 use crystal::CrystalPlugin;
+use door::DoorPlugin;
 use entity::{SpikeBundle, SemiSolidPlatformBundle};
-use misc::{init_start_marker, ButtonBundle, StartFlagBundle};
+use error::LevelDataErrorPlugin;
+use exit::ExitPlugin;
+use misc::{find_entity_instance, init_start_marker, ButtonBundle, StartFlagBundle};
+use playlist::PlaylistPlugin;
+use push_block::PushBlockPlugin;
 use setup::LevelSetupPlugin;
+use shard::ShardPlugin;
 use walls::{spawn_wall_collision, WallBundle};
 
+pub mod carryable;
+pub mod checkpoint;
 pub mod crystal;
+pub mod door;
 pub mod entity;
+pub mod error;
+pub mod exit;
+pub mod ldtk_data;
 pub mod misc;
+pub mod playlist;
+pub mod push_block;
+pub mod registry;
 mod setup;
+pub mod shard;
 mod walls;
 
 /// [`Plugin`] that handles everything related to the level.
@@ -24,8 +44,18 @@ impl Plugin for LevelManagementPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(LdtkPlugin)
             .add_plugins(LevelSetupPlugin)
+            .add_plugins(PlaylistPlugin)
+            .add_plugins(LevelDataErrorPlugin)
             .add_plugins(CrystalPlugin)
+            .add_plugins(CheckpointPlugin)
+            .add_plugins(DoorPlugin)
+            .add_plugins(ExitPlugin)
+            .add_plugins(ShardPlugin)
+            .add_plugins(CarryablePlugin)
+            .add_plugins(PushBlockPlugin)
             .init_resource::<CurrentLevel>()
+            .init_resource::<CompletedLevels>()
+            .init_resource::<PendingTeleport>()
             .register_ldtk_entity::<LdtkPlayerBundle>("Lyra")
             .register_ldtk_entity::<ButtonBundle>("Button")
             .register_ldtk_entity::<StartFlagBundle>("Start")
@@ -37,6 +67,12 @@ impl Plugin for LevelManagementPlugin {
                 (spawn_wall_collision, init_start_marker).in_set(LevelSystems::Processing),
             )
             .add_systems(Update, switch_level)
+            .add_systems(
+                Update,
+                hold_player_for_teleport
+                    .after(switch_level)
+                    .in_set(LevelSystems::Simulation),
+            )
             .configure_sets(
                 PreUpdate,
                 LevelSystems::Processing.after(process_ldtk_levels),
@@ -60,6 +96,28 @@ pub struct CurrentLevel {
     pub world_box: Rect,
 }
 
+/// [`Resource`] that holds the `level_iid` of every level in the loaded project that the player
+/// has completed, by reaching one of its [`Exit`](exit::Exit)s or, for levels without exits, by
+/// moving on to another level.
+#[derive(Default, Resource)]
+pub struct CompletedLevels(pub HashSet<LevelIid>);
+
+/// [`Resource`] holding where the player is being moved to, in a level that may not have spawned
+/// yet. The player is held there until [`switch_level`] has found the level's entity, so that they
+/// don't fall before its terrain has colliders.
+#[derive(Default, Resource)]
+pub struct PendingTeleport(pub Option<Vec2>);
+
+/// The bounds of a level in world coordinates.
+pub fn level_world_box(level: &Level) -> Rect {
+    Rect::new(
+        level.world_x as f32,
+        -level.world_y as f32,
+        (level.world_x + level.px_wid) as f32,
+        (-level.world_y - level.px_hei) as f32,
+    )
+}
+
 /// [`SystemSet`] used to distinguish different types of systems
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LevelSystems {
@@ -71,6 +129,7 @@ pub enum LevelSystems {
 
 /// [`System`] that will run on [`Update`] to check if the Player has moved to another level. If
 /// the player has, then a [`LevelSwitchEvent`] will be sent out to notify other systems.
+#[allow(clippy::too_many_arguments)]
 fn switch_level(
     q_player: Query<&Transform, With<PlayerMarker>>,
     q_level: Query<(Entity, &LevelIid)>,
@@ -79,6 +138,7 @@ fn switch_level(
     ldtk_project_assets: Res<Assets<LdtkProject>>,
     mut ev_reset_level: EventWriter<ResetLevel>,
     mut current_level: ResMut<CurrentLevel>,
+    mut completed_levels: ResMut<CompletedLevels>,
 ) {
     let Ok(transform) = q_player.get_single() else {
         return;
@@ -92,16 +152,16 @@ fn switch_level(
             .get_raw_level_by_iid(&level_iid.to_string())
             .expect("Spawned level should exist in Ldtk project");
 
-        let world_box = Rect::new(
-            level.world_x as f32,
-            -level.world_y as f32,
-            (level.world_x + level.px_wid) as f32,
-            (-level.world_y - level.px_hei) as f32,
-        );
+        let world_box = level_world_box(level);
 
         if world_box.contains(transform.translation.xy()) {
             if current_level.level_iid != *level_iid {
                 if !current_level.level_iid.get().is_empty() {
+                    complete_left_level(
+                        ldtk_project,
+                        &current_level.level_iid,
+                        &mut completed_levels,
+                    );
                     ev_reset_level.send(ResetLevel::Switching);
                 }
 
@@ -111,8 +171,52 @@ fn switch_level(
                     world_box,
                 };
                 *level_selection = LevelSelection::iid(level_iid.to_string());
+            } else if current_level.level_entity != Some(entity) {
+                // the level was selected before it spawned, or it has respawned
+                current_level.level_entity = Some(entity);
             }
             break;
         }
     }
 }
+
+/// Records a level the player has left as completed, unless it has an `Exit`, in which case only
+/// reaching the exit completes it.
+fn complete_left_level(
+    project: &LdtkProject,
+    level_iid: &LevelIid,
+    completed_levels: &mut CompletedLevels,
+) {
+    let has_exit = project
+        .get_raw_level_by_iid(level_iid.get())
+        .is_some_and(|level| find_entity_instance(level, "Exit").is_some());
+    if !has_exit {
+        completed_levels.0.insert(level_iid.clone());
+    }
+}
+
+/// [`System`] that holds the player at the [`PendingTeleport`] until the level they are being
+/// moved to has spawned and become the [`CurrentLevel`].
+fn hold_player_for_teleport(
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut q_player: Query<(&mut Transform, &mut PlayerMovement), With<PlayerMarker>>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Some(pos) = pending_teleport.0 else {
+        return;
+    };
+    // give the level a frame for its terrain to get colliders
+    if current_level.level_entity.is_some() && current_level.world_box.contains(pos) {
+        pending_teleport.0 = None;
+        return;
+    }
+    let Ok((mut transform, mut movement)) = q_player.get_single_mut() else {
+        return;
+    };
+    transform.translation.x = pos.x;
+    transform.translation.y = pos.y;
+    *movement = PlayerMovement {
+        crouching: movement.crouching,
+        ..default()
+    };
+}
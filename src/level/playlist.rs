@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{
+    config::{Config, PlaylistEntry},
+    player::PlayerMarker,
+    shared::ResetLevel,
+};
+
+use super::{
+    checkpoint::ActiveCheckpoints,
+    crystal::{CrystalCache, WorldCrystalLinks},
+    misc::{find_entity_instance, start_flag_position},
+    shard::CollectedShards,
+    switch_level, CompletedLevels, CurrentLevel, LevelSystems, PendingTeleport,
+};
+
+/// [`Plugin`] that plays the projects in the [`LevelConfig`](crate::config::LevelConfig)'s
+/// playlist one after another, moving on to the next project once the player leaves the last
+/// level of the current one.
+pub struct PlaylistPlugin;
+
+impl Plugin for PlaylistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Playlist>().add_systems(
+            Update,
+            advance_playlist
+                .before(switch_level)
+                .in_set(LevelSystems::Simulation),
+        );
+    }
+}
+
+/// [`Resource`] holding the projects in the playlist and which one is being played.
+#[derive(Resource)]
+pub struct Playlist {
+    entries: Vec<PlaylistEntry>,
+    /// Every project in the playlist, loaded at startup so that moving on to the next project
+    /// doesn't have to wait for it to load
+    projects: Vec<Handle<LdtkProject>>,
+    current: usize,
+}
+
+impl FromWorld for Playlist {
+    fn from_world(world: &mut World) -> Self {
+        let entries = world.resource::<Config>().level_config.playlist();
+        let asset_server = world.resource::<AssetServer>();
+        let projects = entries
+            .iter()
+            .map(|entry| asset_server.load(&entry.path))
+            .collect();
+        Playlist {
+            entries,
+            projects,
+            current: 0,
+        }
+    }
+}
+
+impl Playlist {
+    /// The entry of the project being played.
+    pub fn current(&self) -> &PlaylistEntry {
+        &self.entries[self.current]
+    }
+
+    /// The project being played.
+    pub fn current_project(&self) -> Handle<LdtkProject> {
+        self.projects[self.current].clone()
+    }
+
+    /// The index of the entry being played.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn entries(&self) -> &[PlaylistEntry] {
+        &self.entries
+    }
+
+    /// Starts the playlist at another entry, e.g. to continue a save. This has to happen before
+    /// the level is set up at [`Startup`].
+    pub fn resume_at(&mut self, index: usize) {
+        assert!(index < self.entries.len(), "Playlist entry should exist");
+        self.current = index;
+    }
+}
+
+/// [`System`] that moves on to the next project in the [`Playlist`] when the player leaves the
+/// last level of the current one. The player is carried over to the new project's world, so the
+/// new project's Lyra is skipped, and placed at the start flag of its first level.
+///
+/// Projects made from the same template share level iids, so every [`Resource`] keyed by level
+/// iid is cleared.
+#[allow(clippy::too_many_arguments)]
+fn advance_playlist(
+    mut commands: Commands,
+    mut playlist: ResMut<Playlist>,
+    mut q_player: Query<(Entity, &mut Transform, &mut Worldly), With<PlayerMarker>>,
+    q_worlds: Query<Entity, With<LdtkProjectHandle>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+) {
+    // the player is out of the current level while a door or the level select moves them
+    if pending_teleport.0.is_some() {
+        return;
+    }
+    let Some(last_level) = playlist.current().last_level else {
+        return;
+    };
+    let next = playlist.current + 1;
+    let Some(next_entry) = playlist.entries.get(next) else {
+        return;
+    };
+    let Ok((player, mut transform, mut worldly)) = q_player.get_single_mut() else {
+        return;
+    };
+    let Some(project) = ldtk_project_assets.get(&playlist.projects[playlist.current]) else {
+        return;
+    };
+    let Some(level) = project.get_raw_level_at_indices(&LevelIndices::in_root(last_level)) else {
+        return;
+    };
+    if current_level.level_iid.get() != &level.iid
+        || current_level
+            .world_box
+            .contains(transform.translation.truncate())
+    {
+        return;
+    }
+
+    // wait for the next project to finish loading, the player is out of bounds in the meantime
+    let Some(next_project) = ldtk_project_assets.get(&playlist.projects[next]) else {
+        return;
+    };
+    let first_level =
+        next_project.get_raw_level_at_indices(&LevelIndices::in_root(next_entry.first_level));
+    let Some(start) = first_level.and_then(start_flag_position) else {
+        error!(
+            "Level {} of {} has no start flag, staying in {}",
+            next_entry.first_level,
+            next_entry.path,
+            playlist.current().path
+        );
+        playlist.entries.truncate(next);
+        return;
+    };
+
+    // the player is worldly, so Ldtk skips spawning any other entity with the same iid
+    if let Some(lyra) = next_project
+        .iter_raw_levels()
+        .find_map(|level| find_entity_instance(level, "Lyra"))
+    {
+        worldly.entity_iid = lyra.iid.clone();
+    }
+
+    let world = commands
+        .spawn(LdtkWorldBundle {
+            ldtk_handle: playlist.projects[next].clone().into(),
+            ..default()
+        })
+        .id();
+    commands.entity(player).set_parent(world);
+    for old_world in q_worlds.iter() {
+        commands.entity(old_world).despawn_recursive();
+    }
+
+    commands.insert_resource(CrystalCache::default());
+    commands.insert_resource(WorldCrystalLinks::default());
+    commands.insert_resource(ActiveCheckpoints::default());
+    commands.insert_resource(CompletedLevels::default());
+    commands.insert_resource(CollectedShards::default());
+    *current_level = CurrentLevel::default();
+    *level_selection = LevelSelection::index(next_entry.first_level);
+    ev_reset_level.send(ResetLevel::Switching);
+
+    transform.translation.x = start.x;
+    transform.translation.y = start.y;
+    pending_teleport.0 = Some(start);
+
+    playlist.current = next;
+}
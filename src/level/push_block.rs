@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    light::BlocksLight,
+    lighting::occluder::ColliderBasedOccluder,
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::LevelSystems;
+
+/// [`Plugin`] for [`PushBlock`]s, heavy blocks that the player pushes around by walking into
+/// them. The player's [`KinematicCharacterController`] applies impulses to dynamic bodies it runs
+/// into, so no pushing logic is needed here.
+pub struct PushBlockPlugin;
+
+impl Plugin for PushBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PushBlockBundle>("PushBlock")
+            .add_systems(PreUpdate, init_push_blocks.in_set(LevelSystems::Processing))
+            .add_systems(
+                FixedUpdate,
+                reset_push_blocks.run_if(on_event::<ResetLevel>),
+            );
+    }
+}
+
+/// [`Component`] for blocks the player can push. Push blocks are terrain, so the player can
+/// stand on them, and they can rest on crystals.
+#[derive(Default, Component)]
+pub struct PushBlock {
+    /// Where the block is placed in its level, relative to its parent
+    home: Vec3,
+}
+
+/// [`Bundle`] registered with Ldtk to spawn push blocks.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct PushBlockBundle {
+    push_block: PushBlock,
+    #[from_entity_instance]
+    physics: PushBlockPhysicsBundle,
+    blocks_light: BlocksLight,
+    #[with(push_block_sprite)]
+    sprite: Sprite,
+}
+
+/// [`Bundle`] that makes push blocks fall, collide with terrain and cast shadows.
+#[derive(Default, Bundle)]
+struct PushBlockPhysicsBundle {
+    collider: Collider,
+    rigid_body: RigidBody,
+    locked_axes: LockedAxes,
+    velocity: Velocity,
+    friction: Friction,
+    /// Push blocks never sleep, so that they fall as soon as the crystal below them deactivates
+    sleeping: Sleeping,
+    collision_groups: CollisionGroups,
+    occluder: ColliderBasedOccluder,
+}
+
+impl From<&EntityInstance> for PushBlockPhysicsBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        PushBlockPhysicsBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            rigid_body: RigidBody::Dynamic,
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            velocity: Velocity::zero(),
+            friction: Friction::new(0.5),
+            sleeping: Sleeping::disabled(),
+            collision_groups: CollisionGroups::new(GroupLabel::TERRAIN, GroupLabel::ALL),
+            occluder: ColliderBasedOccluder::default(),
+        }
+    }
+}
+
+/// Placeholder [`Sprite`] for push blocks.
+fn push_block_sprite(entity_instance: &EntityInstance) -> Sprite {
+    Sprite {
+        color: Color::srgb(0.35, 0.41, 0.53),
+        custom_size: Some(Vec2::new(
+            entity_instance.width as f32,
+            entity_instance.height as f32,
+        )),
+        ..default()
+    }
+}
+
+/// [`System`] that remembers where each push block was placed, so that it can be put back when
+/// the level is reset.
+fn init_push_blocks(mut q_push_blocks: Query<(&mut PushBlock, &Transform), Added<PushBlock>>) {
+    for (mut push_block, transform) in q_push_blocks.iter_mut() {
+        push_block.home = transform.translation;
+    }
+}
+
+/// [`System`] that puts every [`PushBlock`] back where it was placed on a [`ResetLevel`].
+fn reset_push_blocks(mut q_push_blocks: Query<(&PushBlock, &mut Transform, &mut Velocity)>) {
+    for (push_block, mut transform, mut velocity) in q_push_blocks.iter_mut() {
+        transform.translation = push_block.home;
+        *velocity = Velocity::zero();
+    }
+}
@@ -0,0 +1,37 @@
+/// Int grid values with a bundle registered on the Terrain layer: walls, spikes, crystals and
+/// semi-solid platforms.
+pub const TERRAIN_VALUES: [i32; 11] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 15];
+/// Int grid values with a bundle registered on the Crystalmap layer, the IDs of crystals that
+/// aren't in a [`CrystalGroup`](super::crystal::CrystalGroup).
+pub const CRYSTALMAP_VALUES: [i32; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+/// Entities with a bundle registered with Ldtk.
+pub const ENTITY_IDENTIFIERS: [&str; 13] = [
+    "Lyra",
+    "Button",
+    "Start",
+    "Checkpoint",
+    "Carryable",
+    "PushBlock",
+    "CrystalGroup",
+    "Door",
+    "Warp",
+    "Exit",
+    "Shard",
+    "WindZone",
+    "Strands",
+];
+
+/// The int grid values with a bundle registered on a layer, which is empty for layers that
+/// aren't int grids.
+pub fn registered_int_grid_values(layer_identifier: &str) -> &'static [i32] {
+    match layer_identifier {
+        "Terrain" => &TERRAIN_VALUES,
+        "Crystalmap" => &CRYSTALMAP_VALUES,
+        _ => &[],
+    }
+}
+
+/// Whether an entity has a bundle registered with Ldtk.
+pub fn is_registered_entity(identifier: &str) -> bool {
+    ENTITY_IDENTIFIERS.contains(&identifier)
+}
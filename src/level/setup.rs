@@ -1,7 +1,8 @@
-use crate::config::Config;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
+use super::playlist::Playlist;
+
 pub struct LevelSetupPlugin;
 
 impl Plugin for LevelSetupPlugin {
@@ -18,10 +19,10 @@ impl Plugin for LevelSetupPlugin {
     }
 }
 
-fn setup_level(mut commands: Commands, asset_server: Res<AssetServer>, config: Res<Config>) {
-    commands.insert_resource(LevelSelection::index(config.level_config.level_index));
+fn setup_level(mut commands: Commands, playlist: Res<Playlist>) {
+    commands.insert_resource(LevelSelection::index(playlist.current().first_level));
     commands.spawn(LdtkWorldBundle {
-        ldtk_handle: asset_server.load(&config.level_config.level_path).into(),
+        ldtk_handle: playlist.current_project().into(),
         ..Default::default()
     });
 }
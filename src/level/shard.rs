@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    player::PlayerHurtMarker,
+    shared::{GroupLabel, ResetLevel},
+};
+
+use super::{checkpoint::CheckpointTouched, exit::LevelCompleted, CurrentLevel, LevelSystems};
+
+const SHARD_COLOR: Color = Color::srgb(1.0, 0.9, 0.45);
+const SHARD_GLOW_ALPHA: f32 = 0.3;
+const SHARD_GLOW_SIZE: f32 = 10.0;
+
+/// [`Plugin`] for light [`Shard`]s, which the player collects by touching them. Shards picked up
+/// since the last checkpoint are dropped again if the player respawns, and are only kept once
+/// they reach a checkpoint, an exit, or another level.
+pub struct ShardPlugin;
+
+impl Plugin for ShardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollectedShards>()
+            .register_ldtk_entity::<ShardBundle>("Shard")
+            .add_systems(Startup, spawn_shard_counter)
+            .add_systems(PreUpdate, add_shard_glows.in_set(LevelSystems::Processing))
+            .add_systems(Update, collect_shards.in_set(LevelSystems::Simulation))
+            .add_systems(
+                Update,
+                (
+                    keep_or_drop_shards.after(collect_shards),
+                    show_uncollected_shards,
+                    update_shard_counter,
+                ),
+            );
+    }
+}
+
+/// [`Component`] for light shards.
+#[derive(Default, Component)]
+pub struct Shard {
+    /// The center of the shard in world coordinates, used to tell whether it is in the
+    /// [`CurrentLevel`]
+    center: Vec2,
+}
+
+impl From<&EntityInstance> for Shard {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let center = Vec2::new(
+            entity_instance.world_x.unwrap_or_default() as f32
+                + (0.5 - entity_instance.pivot.x) * entity_instance.width as f32,
+            -(entity_instance.world_y.unwrap_or_default() as f32
+                + (0.5 - entity_instance.pivot.y) * entity_instance.height as f32),
+        );
+        Shard { center }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn shards. The shard is hidden once it is collected,
+/// along with its glow.
+#[derive(Bundle, LdtkEntity)]
+pub struct ShardBundle {
+    #[from_entity_instance]
+    shard: Shard,
+    #[from_entity_instance]
+    sensor: ShardSensorBundle,
+    sprite: Sprite,
+}
+
+impl Default for ShardBundle {
+    fn default() -> Self {
+        Self {
+            shard: Shard::default(),
+            sensor: ShardSensorBundle::default(),
+            sprite: Sprite::from_color(SHARD_COLOR, Vec2::splat(4.0)),
+        }
+    }
+}
+
+/// [`Bundle`] for the [`Sensor`] that collects the shard when the player touches it.
+#[derive(Default, Bundle)]
+struct ShardSensorBundle {
+    collider: Collider,
+    sensor: Sensor,
+    collision_groups: CollisionGroups,
+}
+
+impl From<&EntityInstance> for ShardSensorBundle {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        ShardSensorBundle {
+            collider: Collider::cuboid(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            sensor: Sensor,
+            collision_groups: CollisionGroups::new(GroupLabel::TRIGGER, GroupLabel::PLAYER_SENSOR),
+        }
+    }
+}
+
+/// [`System`] that gives each shard a faint glow behind it. This is a sprite rather than a
+/// [`PointLighting`](crate::lighting::light::PointLighting), since only a few lights can be drawn
+/// at once and a level can have many shards.
+fn add_shard_glows(mut commands: Commands, q_shards: Query<Entity, Added<Shard>>) {
+    for entity in q_shards.iter() {
+        commands.entity(entity).with_child((
+            Sprite::from_color(
+                SHARD_COLOR.with_alpha(SHARD_GLOW_ALPHA),
+                Vec2::splat(SHARD_GLOW_SIZE),
+            ),
+            // draw the glow behind the shard
+            Transform::from_xyz(0.0, 0.0, -0.1),
+        ));
+    }
+}
+
+/// [`Resource`] holding the shards the player has collected in each level.
+#[derive(Default, Resource)]
+pub struct CollectedShards {
+    levels: HashMap<LevelIid, HashSet<EntityIid>>,
+    /// Shards collected since the last checkpoint, which are dropped if the player respawns
+    unconfirmed: Vec<(LevelIid, EntityIid)>,
+}
+
+impl CollectedShards {
+    pub fn contains(&self, shard_iid: &EntityIid) -> bool {
+        self.levels
+            .values()
+            .any(|shards| shards.contains(shard_iid))
+    }
+
+    /// The number of shards collected in a level, including those not yet kept.
+    pub fn count(&self, level_iid: &LevelIid) -> usize {
+        self.levels.get(level_iid).map_or(0, HashSet::len)
+    }
+
+    /// Adds a shard that has already been kept, e.g. when loading a save.
+    pub fn insert(&mut self, level_iid: LevelIid, shard_iid: EntityIid) {
+        self.levels.entry(level_iid).or_default().insert(shard_iid);
+    }
+
+    /// The shards that have been kept, which are the ones that are saved.
+    pub fn kept(&self) -> impl Iterator<Item = &EntityIid> {
+        self.levels
+            .values()
+            .flatten()
+            .filter(|shard_iid| !self.unconfirmed.iter().any(|(_, iid)| iid == *shard_iid))
+    }
+}
+
+/// The number of shards placed in a level.
+pub fn count_level_shards(level: &Level) -> usize {
+    level
+        .layer_instances
+        .iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .filter(|entity| entity.identifier == "Shard")
+        .count()
+}
+
+/// [`System`] that collects the shards in the [`CurrentLevel`] that the player touches.
+fn collect_shards(
+    rapier_context: ReadDefaultRapierContext,
+    q_player: Query<Entity, With<PlayerHurtMarker>>,
+    q_shards: Query<(Entity, &Shard, &EntityIid)>,
+    current_level: Res<CurrentLevel>,
+    mut collected_shards: ResMut<CollectedShards>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    for (entity, shard, iid) in q_shards.iter() {
+        if !current_level.world_box.contains(shard.center)
+            || collected_shards.contains(iid)
+            || rapier_context.intersection_pair(player, entity) != Some(true)
+        {
+            continue;
+        }
+        collected_shards.insert(current_level.level_iid.clone(), iid.clone());
+        collected_shards
+            .unconfirmed
+            .push((current_level.level_iid.clone(), iid.clone()));
+    }
+}
+
+/// [`System`] that keeps the shards collected so far once the player touches a checkpoint, even the
+/// one that is already active, completes the level, or moves on to another one, and drops them
+/// again on a [`ResetLevel::Respawn`], putting them back in the level.
+fn keep_or_drop_shards(
+    mut ev_reset_level: EventReader<ResetLevel>,
+    mut ev_level_completed: EventReader<LevelCompleted>,
+    mut ev_checkpoint_touched: EventReader<CheckpointTouched>,
+    mut collected_shards: ResMut<CollectedShards>,
+) {
+    let (mut respawned, mut switched) = (false, false);
+    for ev in ev_reset_level.read() {
+        match ev {
+            ResetLevel::Respawn => respawned = true,
+            ResetLevel::Switching => switched = true,
+        }
+    }
+    let completed = ev_level_completed.read().count() > 0;
+    let touched_checkpoint = ev_checkpoint_touched.read().count() > 0;
+    if collected_shards.unconfirmed.is_empty() {
+        return;
+    }
+
+    let collected_shards = &mut *collected_shards;
+    if respawned {
+        for (level_iid, shard_iid) in collected_shards.unconfirmed.drain(..) {
+            if let Some(shards) = collected_shards.levels.get_mut(&level_iid) {
+                shards.remove(&shard_iid);
+            }
+        }
+    } else if switched || completed || touched_checkpoint {
+        collected_shards.unconfirmed.clear();
+    }
+}
+
+/// [`System`] that hides collected shards, and shows them again if they are dropped.
+fn show_uncollected_shards(
+    mut q_shards: Query<(&EntityIid, &mut Visibility), With<Shard>>,
+    collected_shards: Res<CollectedShards>,
+) {
+    for (iid, mut visibility) in q_shards.iter_mut() {
+        visibility.set_if_neq(if collected_shards.contains(iid) {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        });
+    }
+}
+
+#[derive(Component)]
+pub struct ShardCounterMarker;
+
+fn spawn_shard_counter(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(SHARD_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(12.0),
+            ..default()
+        },
+        ShardCounterMarker,
+    ));
+}
+
+/// [`System`] that shows the shards collected in the [`CurrentLevel`] out of the shards placed
+/// in it, hiding the counter in levels without shards.
+fn update_shard_counter(
+    mut q_counter: Query<(&mut Text, &mut Visibility), With<ShardCounterMarker>>,
+    collected_shards: Res<CollectedShards>,
+    current_level: Res<CurrentLevel>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if !collected_shards.is_changed() && !current_level.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = q_counter.get_single_mut() else {
+        return;
+    };
+    let level_shards = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+        .and_then(|project| project.get_raw_level_by_iid(current_level.level_iid.get()))
+        .map_or(0, count_level_shards);
+
+    if level_shards == 0 {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+    text.0 = format!(
+        "Shards {}/{level_shards}",
+        collected_shards.count(&current_level.level_iid)
+    );
+}
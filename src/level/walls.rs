@@ -178,6 +178,8 @@ pub fn spawn_wall_collision(
                                     .insert(CollisionGroups::new(
                                         GroupLabel::TERRAIN,
                                         GroupLabel::PLAYER_COLLIDER
+                                            // dynamic bodies like carryables are terrain
+                                            | GroupLabel::TERRAIN
                                             | GroupLabel::LIGHT_RAY
                                             | GroupLabel::WHITE_RAY
                                             | GroupLabel::STRAND
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use bevy::{
+    color::ColorToPacked,
+    input::common_conditions::input_just_pressed,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+
+use crate::{
+    level::{
+        crystal::{crystal_color, is_crystal_active},
+        level_world_box,
+        misc::start_flag_position,
+        shard::{count_level_shards, CollectedShards},
+        CompletedLevels, CurrentLevel, PendingTeleport,
+    },
+    player::PlayerMarker,
+    shared::{GameState, ResetLevel},
+};
+
+/// The width of each level's thumbnail in the menu, in pixels.
+const THUMBNAIL_WIDTH: f32 = 160.0;
+const CARD_COLOR: Color = Color::srgb(0.12, 0.12, 0.16);
+const CARD_HOVERED_COLOR: Color = Color::srgb(0.22, 0.22, 0.3);
+const SHARD_TEXT_COLOR: Color = Color::srgb(1.0, 0.9, 0.45);
+
+/// [`Plugin`] for the level select menu, opened with L. The menu lists every level in the loaded
+/// Ldtk project with a thumbnail of its terrain and the shards collected in it, and clicking on a
+/// level moves the player to its start flag.
+pub struct LevelSelectPlugin;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LevelSelect), spawn_level_select)
+            .add_systems(OnExit(GameState::LevelSelect), despawn_level_select)
+            .add_systems(
+                Update,
+                toggle_level_select.run_if(input_just_pressed(KeyCode::KeyL)),
+            )
+            .add_systems(
+                Update,
+                (highlight_level_buttons, select_level).run_if(in_state(GameState::LevelSelect)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct LevelSelectMarker;
+
+/// [`Component`] for the button of each level in the menu.
+#[derive(Component)]
+pub struct LevelSelectButton {
+    level_iid: String,
+}
+
+fn toggle_level_select(state: Res<State<GameState>>, mut next_state: ResMut<NextState<GameState>>) {
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::LevelSelect),
+        GameState::LevelSelect => next_state.set(GameState::Playing),
+        GameState::Paused | GameState::Respawning | GameState::LevelSummary => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_level_select(
+    mut commands: Commands,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    current_level: Res<CurrentLevel>,
+    completed_levels: Res<CompletedLevels>,
+    collected_shards: Res<CollectedShards>,
+    mut images: ResMut<Assets<Image>>,
+    mut thumbnails: Local<HashMap<(AssetId<LdtkProject>, String), Handle<Image>>>,
+) {
+    let Ok(project_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let project_id: AssetId<LdtkProject> = project_handle.into();
+    let Some(project) = ldtk_project_assets.get(project_id) else {
+        return;
+    };
+    let total_shards: usize = project.iter_raw_levels().map(count_level_shards).sum();
+    let total_collected: usize = project
+        .iter_raw_levels()
+        .map(|level| collected_shards.count(&LevelIid::new(level.iid.clone())))
+        .sum();
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0., 0., 0., 0.85)),
+            LevelSelectMarker,
+        ))
+        .with_children(|menu| {
+            menu.spawn((
+                Text::new("Select a level"),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+            ));
+            if total_shards > 0 {
+                menu.spawn((
+                    Text::new(format!("Shards {total_collected}/{total_shards}")),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(SHARD_TEXT_COLOR),
+                ));
+            }
+            menu.spawn(Node {
+                max_width: Val::Percent(90.0),
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(12.0),
+                row_gap: Val::Px(12.0),
+                ..default()
+            })
+            .with_children(|grid| {
+                for level in project.iter_raw_levels() {
+                    let thumbnail = thumbnails
+                        .entry((project_id, level.iid.clone()))
+                        .or_insert_with(|| images.add(level_thumbnail(level)))
+                        .clone();
+                    let (status, status_color) = if current_level.level_iid.get() == &level.iid {
+                        ("Current", Color::srgb(0.9, 0.9, 0.5))
+                    } else if completed_levels
+                        .0
+                        .contains(&LevelIid::new(level.iid.clone()))
+                    {
+                        ("Completed", Color::srgb(0.5, 0.9, 0.5))
+                    } else {
+                        ("", Color::WHITE)
+                    };
+                    let level_shards = count_level_shards(level);
+                    let shards = if level_shards > 0 {
+                        let collected = collected_shards.count(&LevelIid::new(level.iid.clone()));
+                        format!("Shards {collected}/{level_shards}")
+                    } else {
+                        String::new()
+                    };
+
+                    grid.spawn((
+                        Button,
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::all(Val::Px(6.0)),
+                            row_gap: Val::Px(4.0),
+                            ..default()
+                        },
+                        BackgroundColor(CARD_COLOR),
+                        LevelSelectButton {
+                            level_iid: level.iid.clone(),
+                        },
+                    ))
+                    .with_children(|card| {
+                        card.spawn((
+                            ImageNode::new(thumbnail),
+                            Node {
+                                width: Val::Px(THUMBNAIL_WIDTH),
+                                height: Val::Px(
+                                    THUMBNAIL_WIDTH * level.px_hei as f32 / level.px_wid as f32,
+                                ),
+                                ..default()
+                            },
+                        ));
+                        card.spawn((
+                            Text::new(level.identifier.clone()),
+                            TextFont {
+                                font_size: 12.0,
+                                ..default()
+                            },
+                        ));
+                        card.spawn((
+                            Text::new(status),
+                            TextFont {
+                                font_size: 12.0,
+                                ..default()
+                            },
+                            TextColor(status_color),
+                        ));
+                        card.spawn((
+                            Text::new(shards),
+                            TextFont {
+                                font_size: 12.0,
+                                ..default()
+                            },
+                            TextColor(SHARD_TEXT_COLOR),
+                        ));
+                    });
+                }
+            });
+        });
+}
+
+fn despawn_level_select(mut commands: Commands, query: Query<Entity, With<LevelSelectMarker>>) {
+    let Ok(entity) = query.get_single() else {
+        return;
+    };
+    commands.entity(entity).despawn_recursive();
+}
+
+/// Draws a level's Terrain layer with one pixel per tile.
+fn level_thumbnail(level: &Level) -> Image {
+    let terrain = level
+        .layer_instances
+        .iter()
+        .flatten()
+        .find(|layer| layer.identifier == "Terrain");
+    let (width, height, values) = match terrain {
+        Some(layer) => (layer.c_wid, layer.c_hei, &layer.int_grid_csv[..]),
+        None => (1, 1, &[][..]),
+    };
+
+    let data = (0..(width * height) as usize)
+        .flat_map(|i| {
+            let value = values.get(i).copied().unwrap_or(0);
+            thumbnail_color(IntGridCell { value })
+                .to_srgba()
+                .to_u8_array()
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// The color of a Terrain tile in a level thumbnail. Inactive crystals are drawn faded.
+fn thumbnail_color(cell: IntGridCell) -> Color {
+    if let (Some(color), Some(active)) = (crystal_color(cell), is_crystal_active(cell)) {
+        let color = color.lighting_color();
+        return Color::srgba(color.x, color.y, color.z, if active { 1.0 } else { 0.35 });
+    }
+    match cell.value {
+        1 => Color::srgb(0.55, 0.55, 0.6),
+        2 => Color::srgb(0.8, 0.2, 0.2),
+        15 => Color::srgb(0.5, 0.4, 0.3),
+        _ => Color::srgb(0.05, 0.05, 0.1),
+    }
+}
+
+fn highlight_level_buttons(
+    mut q_buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<LevelSelectButton>),
+    >,
+) {
+    for (interaction, mut background) in q_buttons.iter_mut() {
+        background.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => CARD_HOVERED_COLOR,
+            Interaction::None => CARD_COLOR,
+        };
+    }
+}
+
+/// [`System`] that moves the player to the start flag of the level that was clicked on. The
+/// [`CurrentLevel`] is updated right away, and its entity is filled in by `switch_level` once the
+/// level has spawned.
+#[allow(clippy::too_many_arguments)]
+fn select_level(
+    q_buttons: Query<(&Interaction, &LevelSelectButton), Changed<Interaction>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+) {
+    let Some((_, button)) = q_buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+    let Ok(project_handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let Some(level) = ldtk_project_assets
+        .get(project_handle)
+        .and_then(|project| project.get_raw_level_by_iid(&button.level_iid))
+    else {
+        return;
+    };
+    let Some(start) = start_flag_position(level) else {
+        warn!(
+            "{} has no start flag to move the player to",
+            level.identifier
+        );
+        return;
+    };
+
+    *current_level = CurrentLevel {
+        level_iid: LevelIid::new(level.iid.clone()),
+        level_entity: None,
+        world_box: level_world_box(level),
+    };
+    *level_selection = LevelSelection::iid(level.iid.clone());
+    // move the player right away so that `switch_level` doesn't put them back in the old level
+    if let Ok(mut transform) = q_player.get_single_mut() {
+        transform.translation.x = start.x;
+        transform.translation.y = start.y;
+    }
+    pending_teleport.0 = Some(start);
+    ev_reset_level.send(ResetLevel::Switching);
+    next_state.set(GameState::Playing);
+}
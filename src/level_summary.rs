@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{level::exit::LevelCompleted, shared::GameState};
+
+/// [`Plugin`] for the overlay shown when a level is completed, summarizing the player's attempt
+/// at it. The game stays in [`GameState::LevelSummary`] until Space or Enter is pressed.
+pub struct LevelSummaryPlugin;
+
+impl Plugin for LevelSummaryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LevelSummary), spawn_level_summary)
+            .add_systems(OnExit(GameState::LevelSummary), despawn_level_summary)
+            .add_systems(
+                Update,
+                dismiss_level_summary.run_if(in_state(GameState::LevelSummary)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct LevelSummaryMarker;
+
+fn spawn_level_summary(
+    mut commands: Commands,
+    mut ev_level_completed: EventReader<LevelCompleted>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    let Some(completed) = ev_level_completed.read().last() else {
+        return;
+    };
+    let level_name = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+        .and_then(|project| project.get_raw_level_by_iid(completed.level_iid.get()))
+        .map_or("Level", |level| &level.identifier);
+
+    let lines = [
+        format!("Time: {}", format_time(completed.time)),
+        format!("Deaths: {}", completed.deaths),
+        format!("Beams fired: {}", completed.beams_fired),
+    ];
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0., 0., 0., 0.7)),
+            LevelSummaryMarker,
+        ))
+        .with_children(|summary| {
+            summary.spawn((
+                Text::new(format!("{level_name} complete")),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+            ));
+            for line in lines {
+                summary.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                ));
+            }
+            summary.spawn((
+                Text::new("Press Space to continue"),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.7, 0.7)),
+            ));
+        });
+}
+
+fn despawn_level_summary(mut commands: Commands, query: Query<Entity, With<LevelSummaryMarker>>) {
+    let Ok(entity) = query.get_single() else {
+        return;
+    };
+    commands.entity(entity).despawn_recursive();
+}
+
+fn dismiss_level_summary(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keys.any_just_pressed([KeyCode::Space, KeyCode::Enter]) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Formats a time as minutes, seconds and tenths of a second, e.g. `1:05.3`.
+fn format_time(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
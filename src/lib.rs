@@ -0,0 +1,15 @@
+//! The game's modules, shared by the `lightborne` binary and the level tools in `src/bin`.
+
+pub mod camera;
+pub mod config;
+pub mod debug;
+pub mod input;
+pub mod level;
+pub mod level_select;
+pub mod level_summary;
+pub mod light;
+pub mod lighting;
+pub mod pause;
+pub mod player;
+pub mod save;
+pub mod shared;
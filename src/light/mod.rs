@@ -2,6 +2,7 @@ use bevy::{
     prelude::*,
     sprite::{AlphaMode2d, Material2dPlugin},
 };
+use bevy_rapier2d::prelude::CollisionGroups;
 
 use enum_map::Enum;
 use render::{LightMaterial, LightRenderData};
@@ -10,7 +11,10 @@ use segments::{
 };
 use sensor::{reset_light_sensors, update_light_sensors, HitByLightEvent};
 
-use crate::{level::LevelSystems, shared::ResetLevel};
+use crate::{
+    level::{error::LevelDataErrorKind, LevelSystems},
+    shared::{GroupLabel, ResetLevel},
+};
 
 mod render;
 pub mod segments;
@@ -49,7 +53,7 @@ impl Plugin for LightManagementPlugin {
 }
 
 /// [`Enum`] for each of the light colors.
-#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash)]
+#[derive(Enum, Clone, Copy, Default, PartialEq, Debug, Eq, Hash, PartialOrd, Ord)]
 pub enum LightColor {
     #[default]
     Green,
@@ -82,14 +86,16 @@ impl From<LightColor> for LightMaterial {
     }
 }
 
-impl From<&String> for LightColor {
-    fn from(value: &String) -> Self {
+impl TryFrom<&String> for LightColor {
+    type Error = LevelDataErrorKind;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
         match value.as_str() {
-            "Red" => LightColor::Red,
-            "Green" => LightColor::Green,
-            "White" => LightColor::White,
-            "Blue" => LightColor::Blue,
-            _ => panic!("String {} does not represent Light Color", value),
+            "Red" => Ok(LightColor::Red),
+            "Green" => Ok(LightColor::Green),
+            "White" => Ok(LightColor::White),
+            "Blue" => Ok(LightColor::Blue),
+            _ => Err(LevelDataErrorKind::UnknownLightColor(value.clone())),
         }
     }
 }
@@ -103,6 +109,26 @@ impl LightColor {
         }
     }
 
+    /// The [`CollisionGroups`] of a beam of this color. White beams pass through other beams,
+    /// while every other color reflects off of white beams, and blue beams pass through blue
+    /// crystals.
+    pub fn ray_collision_groups(&self) -> CollisionGroups {
+        match self {
+            LightColor::White => CollisionGroups::new(
+                GroupLabel::WHITE_RAY,
+                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR,
+            ),
+            LightColor::Blue => CollisionGroups::new(
+                GroupLabel::BLUE_RAY,
+                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
+            ),
+            _ => CollisionGroups::new(
+                GroupLabel::LIGHT_RAY,
+                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
+            ),
+        }
+    }
+
     pub fn lighting_color(&self) -> Vec3 {
         match self {
             LightColor::Red => Vec3::new(1.0, 0.1, 0.1),
@@ -125,3 +151,7 @@ pub struct LightRaySource {
     pub color: LightColor,
     pub num_bounces: usize,
 }
+
+/// Marker [`Component`] for terrain that stops light beams instead of reflecting them.
+#[derive(Component, Default)]
+pub struct BlocksLight;
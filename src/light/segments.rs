@@ -5,7 +5,7 @@ use enum_map::EnumMap;
 use super::{
     render::{LightMaterial, LightRenderData},
     sensor::{HitByLightEvent, LightSensor},
-    LightColor, LightRaySource, LIGHT_SPEED,
+    BlocksLight, LightColor, LightRaySource, LIGHT_SPEED,
 };
 use crate::{lighting::light::LineLighting, shared::GroupLabel};
 
@@ -96,6 +96,100 @@ impl FromWorld for LightBounceSfx {
     }
 }
 
+/// Where a light beam traced by [`trace_light_ray`] hit something.
+#[derive(Clone, Copy, Debug)]
+pub struct LightHit<T> {
+    /// What was hit, e.g. an [`Entity`]
+    pub target: T,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub time_of_impact: f32,
+}
+
+/// The path of a light beam traced by [`trace_light_ray`].
+#[derive(Debug)]
+pub struct LightPath<T> {
+    /// The start of the beam, every point it bounced off of, and where it ended. If the beam ran
+    /// out of distance, the last point is not a hit.
+    pub points: Vec<Vec2>,
+    /// Everything the beam hit, in order. `hits[i]` is at `points[i + 1]`.
+    pub hits: Vec<T>,
+}
+
+/// Traces a beam of `color` from `start_pos` in the direction `start_dir`, reflecting off of
+/// everything it hits until it runs out of bounces, travels `max_distance`, or hits something that
+/// `blocks_light`. This doesn't depend on the ECS so that the same tracing is used by the game and
+/// by tools like `lightborne-solve`.
+///
+/// `cast_ray` is called with the position, direction and maximum distance of each ray, as well as
+/// the target hit by the previous ray, which should be excluded so that the beam doesn't hit the
+/// surface it is reflecting off of again.
+pub fn trace_light_ray<T: Copy>(
+    start_pos: Vec2,
+    start_dir: Vec2,
+    color: LightColor,
+    max_distance: f32,
+    mut cast_ray: impl FnMut(Vec2, Vec2, f32, Option<T>) -> Option<LightHit<T>>,
+    mut blocks_light: impl FnMut(T) -> bool,
+) -> LightPath<T> {
+    let mut ray_pos = start_pos;
+    let mut ray_dir = start_dir;
+    let mut remaining_distance = max_distance;
+    let mut last_hit = None;
+
+    let mut path = LightPath {
+        points: vec![ray_pos],
+        hits: Vec::new(),
+    };
+    for _ in 0..color.num_bounces() + 1 {
+        let Some(hit) = cast_ray(ray_pos, ray_dir, remaining_distance, last_hit) else {
+            path.points.push(ray_pos + ray_dir * remaining_distance);
+            break;
+        };
+
+        if hit.time_of_impact < 0.01 {
+            break;
+        }
+
+        remaining_distance -= hit.time_of_impact;
+
+        path.points.push(hit.point);
+        path.hits.push(hit.target);
+
+        if blocks_light(hit.target) {
+            break;
+        }
+
+        ray_pos = hit.point;
+        ray_dir = ray_dir.reflect(hit.normal);
+        last_hit = Some(hit.target);
+    }
+    path
+}
+
+/// Casts a light ray of `color` in the rapier world, for use with [`trace_light_ray`].
+pub fn cast_light_ray(
+    rapier_context: &RapierContext,
+    color: LightColor,
+    ray_pos: Vec2,
+    ray_dir: Vec2,
+    max_distance: f32,
+    exclude: Option<Entity>,
+) -> Option<LightHit<Entity>> {
+    let mut ray_qry = QueryFilter::new().groups(color.ray_collision_groups());
+    if let Some(entity) = exclude {
+        ray_qry = ray_qry.exclude_collider(entity);
+    }
+    let (entity, intersection) =
+        rapier_context.cast_ray_and_get_normal(ray_pos, ray_dir, max_distance, true, ray_qry)?;
+    Some(LightHit {
+        target: entity,
+        point: intersection.point,
+        normal: intersection.normal,
+        time_of_impact: intersection.time_of_impact,
+    })
+}
+
 /// [`System`] that runs on [`Update`], calculating the [`Transform`] of light segments from the
 /// corresponding [`LightRaySource`]. Note that this calculation happens every frame, so instead of
 /// rapidly spawning/despawning the entities, we spawn them and cache them in the
@@ -103,15 +197,13 @@ impl FromWorld for LightBounceSfx {
 ///
 /// If needed, optimization work can be done by recalculating only segments that are currently
 /// changing (segments already "stabilized" usually won't move).
-///
-/// Similar logic is duplicated in [`preview_light_path`](crate::player::light::preview_light_path),
-/// these two systems should be merged.
 pub fn simulate_light_sources(
     mut commands: Commands,
     mut q_light_sources: Query<&mut LightRaySource>,
     mut q_rapier: Query<&mut RapierContext>,
     mut ev_hit_by_light: EventWriter<HitByLightEvent>,
     q_light_sensor: Query<&LightSensor>,
+    q_blocks_light: Query<(), With<BlocksLight>>,
     mut q_segments: Query<(&mut Transform, &mut Visibility), With<LightSegmentMarker>>,
     segment_cache: Res<LightSegmentCache>,
     light_bounce_sfx: Local<LightBounceSfx>,
@@ -121,50 +213,27 @@ pub fn simulate_light_sources(
     };
 
     for mut source in q_light_sources.iter_mut() {
-        let mut ray_pos = source.start_pos;
-        let mut ray_dir = source.start_dir;
-        let collision_groups = match source.color {
-            LightColor::White => CollisionGroups::new(
-                GroupLabel::WHITE_RAY,
-                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR,
-            ),
-            LightColor::Blue => CollisionGroups::new(
-                GroupLabel::BLUE_RAY,
-                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
-            ),
-            _ => CollisionGroups::new(
-                GroupLabel::LIGHT_RAY,
-                GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
-            ),
-        };
-
-        let mut ray_qry = QueryFilter::new().groups(collision_groups);
-
-        let mut pts: Vec<Vec2> = vec![ray_pos];
-        let mut remaining_time = source.time_traveled;
-        let mut bounces = 0;
-        for _ in 0..source.color.num_bounces() + 1 {
-            let Some((entity, intersection)) = rapier_context.cast_ray_and_get_normal(
-                ray_pos,
-                ray_dir,
-                remaining_time,
-                true,
-                ray_qry,
-            ) else {
-                let final_point = ray_pos + ray_dir * remaining_time;
-                pts.push(final_point);
-                break;
-            };
-
-            if intersection.time_of_impact < 0.01 {
-                break;
-            }
-
-            remaining_time -= intersection.time_of_impact;
-
-            pts.push(intersection.point);
-
-            bounces += 1;
+        let color = source.color;
+        let path = trace_light_ray(
+            source.start_pos,
+            source.start_dir,
+            color,
+            source.time_traveled,
+            |ray_pos, ray_dir, max_distance, exclude| {
+                cast_light_ray(
+                    &rapier_context,
+                    color,
+                    ray_pos,
+                    ray_dir,
+                    max_distance,
+                    exclude,
+                )
+            },
+            |entity| q_blocks_light.contains(entity),
+        );
+
+        for (i, &entity) in path.hits.iter().enumerate() {
+            let bounces = i + 1;
             if bounces > source.num_bounces {
                 source.num_bounces = bounces;
                 // Add sound effects as child because this current entity could have been hit again
@@ -174,14 +243,11 @@ pub fn simulate_light_sources(
                 ));
             }
 
-            if let Ok(_) = q_light_sensor.get(entity) {
+            if q_light_sensor.contains(entity) {
                 ev_hit_by_light.send(HitByLightEvent(entity));
-            };
-
-            ray_pos = intersection.point;
-            ray_dir = ray_dir.reflect(intersection.normal);
-            ray_qry = ray_qry.exclude_collider(entity);
+            }
         }
+        let pts = path.points;
 
         for (i, segment) in segment_cache.table[source.color].iter().enumerate() {
             let Ok((mut c_transform, mut c_visibility)) = q_segments.get_mut(*segment) else {
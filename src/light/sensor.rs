@@ -53,22 +53,23 @@ pub struct LightSensorBundle {
     light_sensor: LightSensor,
 }
 
+/// Buttons with a missing or unknown color or id are given placeholders, and skipped by their
+/// [`LevelDataCheck`](crate::level::error::LevelDataCheck).
 impl From<&EntityInstance> for LightSensorBundle {
     fn from(entity_instance: &EntityInstance) -> Self {
         match entity_instance.identifier.as_ref() {
             "Button" => {
-                let light_color: LightColor = entity_instance
+                let light_color = entity_instance
                     .get_enum_field("light_color")
-                    .expect("light_color needs to be an enum field on all buttons")
-                    .into();
+                    .ok()
+                    .and_then(|color| LightColor::try_from(color).ok())
+                    .unwrap_or_default();
 
-                let id = entity_instance
-                    .get_int_field("id")
-                    .expect("id needs to be an int field on all buttons");
+                let id = entity_instance.get_int_field("id").copied().unwrap_or(0);
 
                 let sensor_color = CrystalColor {
                     color: light_color,
-                    id: *id,
+                    id,
                 };
 
                 return Self {
@@ -104,7 +105,7 @@ pub fn reset_light_sensors(mut q_sensors: Query<&mut LightSensor>) {
 /// implementation across multiple systems to better utilize [`Event`].
 pub fn update_light_sensors(
     mut commands: Commands,
-    mut q_sensors: Query<(Entity, &mut LightSensor)>,
+    mut q_sensors: Query<(Entity, &mut LightSensor, &GlobalTransform)>,
     mut ev_hit_by_light: EventReader<HitByLightEvent>,
     mut ev_crystal_toggle: EventWriter<CrystalToggleEvent>,
     asset_server: Res<AssetServer>,
@@ -115,7 +116,7 @@ pub fn update_light_sensors(
         hit_sensors.insert(ev.0);
     }
 
-    for (entity, mut sensor) in q_sensors.iter_mut() {
+    for (entity, mut sensor, transform) in q_sensors.iter_mut() {
         let was_hit = hit_sensors.contains(&entity);
 
         if was_hit {
@@ -135,6 +136,7 @@ pub fn update_light_sensors(
         if sensor.activation_timer.just_finished() {
             ev_crystal_toggle.send(CrystalToggleEvent {
                 color: sensor.toggle_color,
+                origin: transform.translation().truncate(),
             });
 
             commands.entity(entity).with_child((
@@ -3,27 +3,21 @@ use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy_rapier2d::prelude::*;
 
-use camera::CameraPlugin;
-use config::ConfigPlugin;
-use debug::DebugPlugin;
-use input::{init_cursor_world_coords, update_cursor_world_coords};
-use level::LevelManagementPlugin;
-use light::LightManagementPlugin;
-use lighting::LightingPlugin;
-use pause::PausePlugin;
-use player::PlayerManagementPlugin;
-use shared::{GameState, ResetLevel};
-
-mod camera;
-mod config;
-mod debug;
-mod input;
-mod level;
-mod light;
-mod lighting;
-mod pause;
-mod player;
-mod shared;
+use lightborne::{
+    camera::CameraPlugin,
+    config::ConfigPlugin,
+    debug::DebugPlugin,
+    input::{init_cursor_world_coords, update_cursor_world_coords},
+    level::LevelManagementPlugin,
+    level_select::LevelSelectPlugin,
+    level_summary::LevelSummaryPlugin,
+    light::LightManagementPlugin,
+    lighting::LightingPlugin,
+    pause::PausePlugin,
+    player::PlayerManagementPlugin,
+    save::SavePlugin,
+    shared::{GameState, ResetLevel, RespawnState},
+};
 
 fn main() {
     App::new()
@@ -50,10 +44,14 @@ fn main() {
         .add_plugins(LevelManagementPlugin)
         .add_plugins(LightManagementPlugin)
         .add_plugins(PausePlugin)
+        .add_plugins(LevelSelectPlugin)
+        .add_plugins(LevelSummaryPlugin)
+        .add_plugins(SavePlugin)
         .add_plugins(CameraPlugin)
         .add_plugins(LightingPlugin)
         .add_plugins(DebugPlugin::default())
         .insert_state(GameState::Playing)
+        .add_sub_state::<RespawnState>()
         .add_event::<ResetLevel>()
         .add_systems(Startup, init_cursor_world_coords)
         .add_systems(Update, update_cursor_world_coords)
@@ -45,8 +45,14 @@ fn despawn_pause(mut commands: Commands, query: Query<Entity, With<PauseMarker>>
 }
 
 fn toggle_pause(state: Res<State<GameState>>, mut next_state: ResMut<NextState<GameState>>) {
-    next_state.set(match state.get() {
-        GameState::Paused => GameState::Playing,
-        GameState::Playing => GameState::Paused,
-    })
+    match state.get() {
+        GameState::Paused => next_state.set(GameState::Playing),
+        GameState::Playing => next_state.set(GameState::Paused),
+        // let the death sequence finish before pausing
+        GameState::Respawning => {}
+        // the summary is dismissed with space or enter
+        GameState::LevelSummary => {}
+        // escape closes the level select menu
+        GameState::LevelSelect => next_state.set(GameState::Playing),
+    }
 }
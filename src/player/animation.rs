@@ -0,0 +1,349 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{level::LevelSystems, light::LightRaySource};
+
+use super::{
+    movement::{PlayerMovement, PlayerState},
+    PlayerMarker,
+};
+
+/// The path to Lyra's [`AnimationSheet`].
+const PLAYER_ANIMATION_SHEET_PATH: &str = "animations/lyra.anim.toml";
+
+/// [`Plugin`] that loads [`AnimationSheet`]s and plays the player's animations.
+pub struct PlayerAnimationPlugin;
+
+impl Plugin for PlayerAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationSheet>()
+            .init_asset_loader::<AnimationSheetLoader>()
+            .init_resource::<PlayerAnimationSheet>()
+            .add_systems(Update, animate_player.in_set(LevelSystems::Simulation));
+    }
+}
+
+/// A single animation in an [`AnimationSheet`], playing the frames `first` through `last`
+/// (inclusive) of the sheet.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnimationData {
+    first: usize,
+    last: usize,
+    /// Frames per second
+    fps: f32,
+    /// Whether the animation restarts after the last frame. Animations that don't loop hold their
+    /// last frame once finished.
+    #[serde(default = "default_true")]
+    looping: bool,
+    /// Whether the animation can be cut off before it finishes.
+    #[serde(default = "default_true")]
+    interruptible: bool,
+    /// The name of the animation to play once a non-looping animation finishes.
+    next: Option<String>,
+    /// Path to a separate image to take this animation's frames from instead of the sheet's
+    /// image, relative to the assets folder. Its frames are laid out in a single row.
+    image: Option<String>,
+    /// The size of a frame in `image`, defaulting to the sheet's frame size.
+    frame_size: Option<[u32; 2]>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl AnimationData {
+    fn len(&self) -> usize {
+        self.last - self.first + 1
+    }
+}
+
+/// The format of an [`AnimationSheet`] on disk (`*.anim.toml`).
+#[derive(Deserialize)]
+struct AnimationSheetFile {
+    /// Path to the spritesheet, relative to the assets folder
+    image: String,
+    frame_size: [u32; 2],
+    columns: u32,
+    rows: u32,
+    animations: HashMap<String, AnimationData>,
+}
+
+/// [`Asset`] that holds a spritesheet split up into frames, as well as named animations over
+/// those frames. Loaded from `*.anim.toml` files so that animations can be added or tweaked
+/// without changing any code.
+#[derive(Asset, TypePath, Debug)]
+pub struct AnimationSheet {
+    image: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+    animations: HashMap<String, AnimationData>,
+    /// The image and layout of each animation that has its own `image`
+    textures: HashMap<String, (Handle<Image>, Handle<TextureAtlasLayout>)>,
+}
+
+impl AnimationSheet {
+    /// The image and layout that the frames of the animation with the given name come from.
+    fn texture(&self, name: &str) -> (&Handle<Image>, &Handle<TextureAtlasLayout>) {
+        self.textures
+            .get(name)
+            .map(|(image, layout)| (image, layout))
+            .unwrap_or((&self.image, &self.layout))
+    }
+
+    /// The animation with the given name, falling back to `idle` if it doesn't exist.
+    fn get(&self, name: &str) -> Option<(&str, &AnimationData)> {
+        self.animations
+            .get_key_value(name)
+            .or_else(|| self.animations.get_key_value("idle"))
+            .map(|(name, data)| (name.as_str(), data))
+    }
+}
+
+#[derive(Default)]
+struct AnimationSheetLoader;
+
+impl AssetLoader for AnimationSheetLoader {
+    type Asset = AnimationSheet;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: AnimationSheetFile = toml::from_str(std::str::from_utf8(&bytes)?)?;
+
+        let num_frames = (file.columns * file.rows) as usize;
+        let mut textures = HashMap::new();
+        for (name, animation) in file.animations.iter() {
+            if let Some(image) = &animation.image {
+                // frames of a separate image are laid out in a single row, so there is always
+                // room for `last`
+                let layout = TextureAtlasLayout::from_grid(
+                    UVec2::from(animation.frame_size.unwrap_or(file.frame_size)),
+                    animation.last as u32 + 1,
+                    1,
+                    None,
+                    None,
+                );
+                textures.insert(
+                    name.clone(),
+                    (
+                        load_context.load(image),
+                        load_context.add_labeled_asset(format!("{name}_layout"), layout),
+                    ),
+                );
+            }
+            if animation.first > animation.last
+                || (animation.image.is_none() && animation.last >= num_frames)
+            {
+                return Err(format!(
+                    "Animation {name} has frames {}..={}, but the sheet only has {num_frames} frames",
+                    animation.first, animation.last
+                )
+                .into());
+            }
+            if animation.fps <= 0.0 {
+                return Err(format!("Animation {name} needs a positive fps").into());
+            }
+            if let Some(next) = &animation.next {
+                if !file.animations.contains_key(next) {
+                    return Err(
+                        format!("Animation {name} has unknown next animation {next}").into(),
+                    );
+                }
+            }
+        }
+
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::from(file.frame_size),
+            file.columns,
+            file.rows,
+            None,
+            None,
+        );
+
+        Ok(AnimationSheet {
+            image: load_context.load(file.image),
+            layout: load_context.add_labeled_asset("layout".to_string(), layout),
+            animations: file.animations,
+            textures,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.toml"]
+    }
+}
+
+/// [`Resource`] holding the handle to the player's [`AnimationSheet`].
+#[derive(Resource)]
+pub struct PlayerAnimationSheet(Handle<AnimationSheet>);
+
+impl FromWorld for PlayerAnimationSheet {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        PlayerAnimationSheet(asset_server.load(PLAYER_ANIMATION_SHEET_PATH))
+    }
+}
+
+/// The animations the player can be in, determined from the [`PlayerState`] and the player's
+/// inputs. Each corresponds to the animation in the [`AnimationSheet`] with the same name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAnimationType {
+    #[default]
+    Idle,
+    Moving,
+    Jumping,
+    Falling,
+    Crouching,
+    Dashing,
+    Aiming,
+    Shooting,
+}
+
+impl PlayerAnimationType {
+    /// The name of the animation in the [`AnimationSheet`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerAnimationType::Idle => "idle",
+            PlayerAnimationType::Moving => "moving",
+            PlayerAnimationType::Jumping => "jumping",
+            PlayerAnimationType::Falling => "falling",
+            PlayerAnimationType::Crouching => "crouching",
+            PlayerAnimationType::Dashing => "dashing",
+            PlayerAnimationType::Aiming => "aiming",
+            PlayerAnimationType::Shooting => "shooting",
+        }
+    }
+}
+
+/// [`Component`] that stores the animation the player is currently playing.
+#[derive(Component, Default)]
+pub struct PlayerAnimation {
+    /// The name of the current animation, empty before the first animation starts playing
+    current: String,
+    /// The current frame, relative to the first frame of the animation
+    frame: usize,
+    timer: Timer,
+    finished: bool,
+}
+
+impl PlayerAnimation {
+    fn play(&mut self, name: &str, animation: &AnimationData) {
+        self.current = name.to_string();
+        self.frame = 0;
+        self.timer = Timer::from_seconds(1.0 / animation.fps, TimerMode::Repeating);
+        self.finished = false;
+    }
+
+    fn tick(&mut self, delta: Duration, animation: &AnimationData) {
+        // the sheet could have been hot reloaded with fewer frames
+        self.frame = self.frame.min(animation.len() - 1);
+        if self.finished {
+            return;
+        }
+
+        self.timer.tick(delta);
+        for _ in 0..self.timer.times_finished_this_tick() {
+            if self.frame + 1 < animation.len() {
+                self.frame += 1;
+            } else if animation.looping {
+                self.frame = 0;
+            } else {
+                self.finished = true;
+                break;
+            }
+        }
+    }
+}
+
+/// [`System`] that picks the player's animation based on the [`PlayerState`] and inputs, then
+/// advances it and updates the player [`Sprite`] to show the current frame.
+pub fn animate_player(
+    mut q_player: Query<
+        (
+            &mut Sprite,
+            &mut PlayerAnimation,
+            &PlayerState,
+            &PlayerMovement,
+        ),
+        With<PlayerMarker>,
+    >,
+    q_new_light_sources: Query<(), Added<LightRaySource>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    player_sheet: Res<PlayerAnimationSheet>,
+    sheets: Res<Assets<AnimationSheet>>,
+    time: Res<Time>,
+) {
+    let Some(sheet) = sheets.get(&player_sheet.0) else {
+        return;
+    };
+    let Ok((mut sprite, mut animation, state, movement)) = q_player.get_single_mut() else {
+        return;
+    };
+
+    let desired = if !q_new_light_sources.is_empty() {
+        PlayerAnimationType::Shooting
+    } else if movement.crouching {
+        PlayerAnimationType::Crouching
+    } else if mouse.pressed(MouseButton::Left)
+        && matches!(state, PlayerState::Idle | PlayerState::Moving)
+    {
+        PlayerAnimationType::Aiming
+    } else {
+        match state {
+            PlayerState::Idle => PlayerAnimationType::Idle,
+            PlayerState::Moving => PlayerAnimationType::Moving,
+            PlayerState::Jumping => PlayerAnimationType::Jumping,
+            PlayerState::Falling => PlayerAnimationType::Falling,
+            PlayerState::Dashing => PlayerAnimationType::Dashing,
+        }
+    }
+    .name();
+
+    let current = sheet.get(&animation.current).map(|(_, data)| data);
+    let next = if animation.finished {
+        current
+            .and_then(|data| data.next.as_deref())
+            .or(Some(desired))
+    } else if current.is_none_or(|data| data.interruptible) {
+        Some(desired)
+    } else {
+        None
+    };
+    if let Some((name, data)) = next.and_then(|next| sheet.get(next)) {
+        if name != animation.current {
+            animation.play(name, data);
+        }
+    }
+
+    let Some((name, data)) = sheet.get(&animation.current) else {
+        return;
+    };
+    animation.tick(time.delta(), data);
+    let index = data.first + animation.frame;
+    let (image, layout) = sheet.texture(name);
+
+    if sprite.image != *image {
+        sprite.image = image.clone();
+    }
+    let atlas_matches = sprite
+        .texture_atlas
+        .as_ref()
+        .is_some_and(|atlas| atlas.layout == *layout && atlas.index == index);
+    if !atlas_matches {
+        sprite.texture_atlas = Some(TextureAtlas {
+            layout: layout.clone(),
+            index,
+        });
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use crate::lighting::light::PointLighting;
+
+use super::{light::PlayerLightInventory, movement::PlayerMovement, PlayerMarker};
+
+/// The number of [`FixedUpdate`] steps between each afterimage spawned during a dash.
+const AFTERIMAGE_INTERVAL_TICKS: u32 = 2;
+/// How long an afterimage takes to fade away.
+const AFTERIMAGE_LIFETIME_SECS: f32 = 0.3;
+const AFTERIMAGE_ALPHA: f32 = 0.6;
+const DASH_LIGHT_RADIUS: f32 = 24.0;
+
+/// [`Component`] for the copies of the player sprite left behind while dashing. Each afterimage
+/// is tinted in the player's current light color, and fades away over
+/// [`AFTERIMAGE_LIFETIME_SECS`].
+#[derive(Component)]
+pub struct DashAfterimage {
+    timer: Timer,
+}
+
+/// [`Component`] for the light that follows the player while they dash, in their current light
+/// color. There is only ever one, as every [`PointLighting`] takes up one of the few lights that
+/// can be drawn at once. It fades away over [`AFTERIMAGE_LIFETIME_SECS`] once the dash ends.
+#[derive(Component)]
+pub struct DashLight {
+    timer: Timer,
+}
+
+/// [`System`] that leaves an afterimage of the player behind every few steps of a dash.
+pub fn spawn_dash_afterimages(
+    mut commands: Commands,
+    q_player: Query<
+        (&Transform, &Sprite, &PlayerMovement, &PlayerLightInventory),
+        With<PlayerMarker>,
+    >,
+    mut dash_ticks: Local<u32>,
+) {
+    let Ok((transform, sprite, movement, inventory)) = q_player.get_single() else {
+        return;
+    };
+    if !movement.is_dashing() {
+        *dash_ticks = 0;
+        return;
+    }
+
+    // spawn an afterimage on the first step of the dash, then every few steps after
+    *dash_ticks += 1;
+    if (*dash_ticks - 1) % AFTERIMAGE_INTERVAL_TICKS != 0 {
+        return;
+    }
+
+    let color = inventory.current_color();
+    commands.spawn((
+        DashAfterimage {
+            timer: Timer::from_seconds(AFTERIMAGE_LIFETIME_SECS, TimerMode::Once),
+        },
+        Sprite {
+            color: Color::from(color).with_alpha(AFTERIMAGE_ALPHA),
+            ..sprite.clone()
+        },
+        // draw afterimages behind the player
+        Transform::from_translation(transform.translation - Vec3::Z * 0.1),
+    ));
+}
+
+/// [`System`] that lights up the player while they dash, keeping the [`DashLight`] at full
+/// strength until the dash ends.
+pub fn light_dashes(
+    mut commands: Commands,
+    q_player: Query<(Entity, &PlayerMovement, &PlayerLightInventory), With<PlayerMarker>>,
+    mut q_dash_light: Query<(&mut DashLight, &mut PointLighting)>,
+) {
+    let Ok((player, movement, inventory)) = q_player.get_single() else {
+        return;
+    };
+    if !movement.is_dashing() {
+        return;
+    }
+
+    let color = inventory.current_color().lighting_color();
+    if let Ok((mut dash_light, mut light)) = q_dash_light.get_single_mut() {
+        dash_light.timer.reset();
+        light.color = color;
+        return;
+    }
+    commands.entity(player).with_child((
+        DashLight {
+            timer: Timer::from_seconds(AFTERIMAGE_LIFETIME_SECS, TimerMode::Once),
+        },
+        PointLighting {
+            radius: DASH_LIGHT_RADIUS,
+            color,
+        },
+    ));
+}
+
+/// [`System`] that fades out [`DashAfterimage`]s and the [`DashLight`], despawning them once they
+/// have faded away completely.
+pub fn fade_dash_afterimages(
+    mut commands: Commands,
+    mut q_afterimages: Query<(Entity, &mut DashAfterimage, &mut Sprite)>,
+    mut q_dash_light: Query<(Entity, &mut DashLight, &mut PointLighting)>,
+    time: Res<Time>,
+) {
+    for (entity, mut afterimage, mut sprite) in q_afterimages.iter_mut() {
+        afterimage.timer.tick(time.delta());
+        if afterimage.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let remaining = afterimage.timer.fraction_remaining();
+        sprite.color.set_alpha(AFTERIMAGE_ALPHA * remaining);
+    }
+
+    for (entity, mut dash_light, mut light) in q_dash_light.iter_mut() {
+        dash_light.timer.tick(time.delta());
+        if dash_light.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        light.radius = DASH_LIGHT_RADIUS * dash_light.timer.fraction_remaining();
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::Config,
+    level::LevelSystems,
+    shared::{GameState, ResetLevel},
+};
+
+use super::PlayerMarker;
+
+/// How long the player stays visible or hidden for while flashing after taking damage.
+const INVULNERABILITY_FLASH_SECS: f32 = 0.08;
+/// The alpha of the player sprite during the hidden part of a flash.
+const INVULNERABILITY_FLASH_ALPHA: f32 = 0.2;
+
+/// [`Plugin`] for the player's [`Health`], used when the
+/// [`Difficulty`](crate::config::Difficulty) is set to `Health`.
+pub struct PlayerHealthPlugin;
+
+impl Plugin for PlayerHealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            add_player_health.in_set(LevelSystems::Processing),
+        )
+        .add_systems(
+            Update,
+            flash_invulnerable_player.in_set(LevelSystems::Simulation),
+        )
+        .add_systems(
+            FixedUpdate,
+            reset_player_health.run_if(on_event::<ResetLevel>),
+        )
+        // the player is hidden during the death sequence, so stop flashing
+        .add_systems(OnEnter(GameState::Respawning), remove_invulnerability);
+    }
+}
+
+/// [`Component`] that stores the player's health.
+#[derive(Component, Debug)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { current: max, max }
+    }
+
+    /// Takes away `damage` health, returning whether the player has run out of health.
+    pub fn damage(&mut self, damage: u32) -> bool {
+        self.current = self.current.saturating_sub(damage);
+        self.current == 0
+    }
+}
+
+/// [`Component`] added to the player after taking damage. The player can't be hurt, and flashes,
+/// until the timer finishes.
+#[derive(Component)]
+pub struct Invulnerable {
+    timer: Timer,
+}
+
+impl Invulnerable {
+    pub fn from_seconds(secs: f32) -> Self {
+        Invulnerable {
+            timer: Timer::from_seconds(secs, TimerMode::Once),
+        }
+    }
+}
+
+fn add_player_health(
+    mut commands: Commands,
+    q_player: Query<Entity, Added<PlayerMarker>>,
+    config: Res<Config>,
+) {
+    let Ok(player) = q_player.get_single() else {
+        return;
+    };
+    commands
+        .entity(player)
+        .insert(Health::new(config.health_config.max_health));
+}
+
+/// [`System`] that flashes the player while they are [`Invulnerable`], removing the component
+/// once it runs out.
+fn flash_invulnerable_player(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Invulnerable, &mut Sprite), With<PlayerMarker>>,
+    time: Res<Time>,
+) {
+    let Ok((player, mut invulnerable, mut sprite)) = q_player.get_single_mut() else {
+        return;
+    };
+    invulnerable.timer.tick(time.delta());
+    if invulnerable.timer.finished() {
+        sprite.color.set_alpha(1.0);
+        commands.entity(player).remove::<Invulnerable>();
+        return;
+    }
+
+    let flash = (invulnerable.timer.elapsed_secs() / INVULNERABILITY_FLASH_SECS) as u32;
+    sprite.color.set_alpha(if flash % 2 == 0 {
+        INVULNERABILITY_FLASH_ALPHA
+    } else {
+        1.0
+    });
+}
+
+fn remove_invulnerability(
+    mut commands: Commands,
+    mut q_player: Query<(Entity, &mut Sprite), (With<PlayerMarker>, With<Invulnerable>)>,
+) {
+    let Ok((player, mut sprite)) = q_player.get_single_mut() else {
+        return;
+    };
+    sprite.color.set_alpha(1.0);
+    commands.entity(player).remove::<Invulnerable>();
+}
+
+/// [`System`] that restores the player's health on a [`ResetLevel`], both when respawning and
+/// when moving on to a new level.
+fn reset_player_health(mut q_player: Query<&mut Health, With<PlayerMarker>>, config: Res<Config>) {
+    let Ok(mut health) = q_player.get_single_mut() else {
+        return;
+    };
+    *health = Health::new(config.health_config.max_health);
+}
@@ -3,26 +3,54 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 
 use crate::{
+    config::{Config, Difficulty},
     level::{
+        checkpoint::ActiveCheckpoints,
         entity::{HurtMarker, Spike},
+        error::{LevelDataError, LevelDataErrorKind},
         misc::StartFlag,
         CurrentLevel,
     },
-    shared::{GameState, ResetLevel},
+    shared::ResetLevel,
 };
 
 use super::{
-    light::PlayerLightInventory, movement::PlayerMovement, PlayerHurtMarker, PlayerMarker,
+    health::{Health, Invulnerable},
+    light::PlayerLightInventory,
+    movement::PlayerMovement,
+    PlayerHurtMarker, PlayerMarker,
 };
 
-/// [`System`] that runs on [`GameState::Respawning`]. Will turn the state back into playing
-/// immediately.
+/// Why the player died.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    /// The player touched a hazard, e.g. spikes
+    #[default]
+    Hazard,
+    /// A crystal turned solid inside of the player
+    Crushed,
+    /// The player restarted from the last checkpoint with R
+    Reset,
+}
+
+/// [`Event`] sent to kill the player, starting the death sequence in
+/// [`GameState::Respawning`](crate::shared::GameState::Respawning).
+#[derive(Event, Default)]
+pub struct KillPlayerEvent {
+    pub cause: DeathCause,
+}
+
+/// [`System`] that moves the player back to the active
+/// [`Checkpoint`](crate::level::checkpoint::Checkpoint) of the current level, or
+/// its [`StartFlag`] if no checkpoint has been activated, on a [`ResetLevel::Respawn`]. This is
+/// sent once the screen has faded out during the death sequence.
 pub fn reset_player_position(
     mut q_player: Query<&mut Transform, With<PlayerMarker>>,
-    mut next_game_state: ResMut<NextState<GameState>>,
     mut ev_reset_level: EventReader<ResetLevel>,
     q_start_flag: Query<(&StartFlag, &EntityInstance)>,
     current_level: Res<CurrentLevel>,
+    active_checkpoints: Res<ActiveCheckpoints>,
+    mut ev_level_data_error: EventWriter<LevelDataError>,
 ) {
     // check that we recieved a ResetLevel event asking us to Respawn
     if !ev_reset_level.read().any(|x| *x == ResetLevel::Respawn) {
@@ -32,20 +60,31 @@ pub fn reset_player_position(
         return;
     };
 
-    next_game_state.set(GameState::Playing);
+    if let Some(checkpoint) = active_checkpoints.get(&current_level.level_iid) {
+        transform.translation.x = checkpoint.respawn_point.x;
+        transform.translation.y = checkpoint.respawn_point.y;
+        return;
+    }
 
     for (flag, instance) in q_start_flag.iter() {
+        // start flags without a world position are skipped when they spawn
+        let (Some(world_x), Some(world_y)) = (instance.world_x, instance.world_y) else {
+            continue;
+        };
         if current_level.level_iid == flag.level_iid {
-            transform.translation.x =
-                instance.world_x.expect("Lightborne uses Free world layout") as f32;
-            transform.translation.y =
-                -instance.world_y.expect("Lightborne uses Free world layout") as f32 + 3.0;
+            transform.translation.x = world_x as f32;
             // add small height so Lyra is not stuck into the floor
+            transform.translation.y = -world_y as f32 + 3.0;
             return;
         }
     }
 
-    panic!("Couldn't find start flag to respawn at");
+    ev_level_data_error.send(LevelDataError {
+        kind: LevelDataErrorKind::NoRespawnPoint,
+        level_iid: Some(current_level.level_iid.clone()),
+        entity_iid: None,
+        grid_coords: None,
+    });
 }
 
 /// Resets the player inventory and movement information on a [`LevelSwitchEvent`]
@@ -56,29 +95,79 @@ pub fn reset_player_on_level_switch(
         return;
     };
 
-    *movement = PlayerMovement::default();
+    // the player's collider is only swapped back by `crouch_player`, so keep the crouch state
+    *movement = PlayerMovement {
+        crouching: movement.crouching,
+        ..default()
+    };
     *inventory = PlayerLightInventory::default();
 }
 
-/// Kills player upon touching a HURT_BOX
-pub fn kill_player_on_spike(
+/// The velocity the player is knocked back with when taking damage, facing away from the hazard.
+const KNOCKBACK_VEL: Vec2 = Vec2::new(2.5, 1.8);
+
+/// Hurts the player upon touching a HURT_BOX, unless the player is invulnerable from dashing or
+/// from recently taking damage. With [`Difficulty::OneHit`] the player dies immediately. With
+/// [`Difficulty::Health`] the player loses health and is knocked away from the hazard, only dying
+/// once they run out of health.
+pub fn hurt_player(
+    mut commands: Commands,
     rapier_context: Query<&RapierContext>,
-    q_player: Query<Entity, With<PlayerHurtMarker>>,
-    mut q_hurt: Query<(&mut Spike, Entity), With<HurtMarker>>,
-    mut ev_reset_level: EventWriter<ResetLevel>,
+    q_player_hurtbox: Query<Entity, With<PlayerHurtMarker>>,
+    mut q_player: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut PlayerMovement,
+            &mut Health,
+            Has<Invulnerable>,
+        ),
+        With<PlayerMarker>,
+    >,
+    mut q_hurt: Query<(Entity, &HurtMarker, &GlobalTransform, Option<&mut Spike>)>,
+    mut ev_kill_player: EventWriter<KillPlayerEvent>,
+    config: Res<Config>,
 ) {
     let Ok(rapier) = rapier_context.get_single() else {
         return;
     };
-    let Ok(player) = q_player.get_single() else {
+    let Ok(hurtbox) = q_player_hurtbox.get_single() else {
         return;
     };
+    let Ok((player, player_transform, mut movement, mut health, invulnerable)) =
+        q_player.get_single_mut()
+    else {
+        return;
+    };
+    if movement.is_invulnerable() || invulnerable {
+        return;
+    }
 
-    for (mut spike, hurt) in q_hurt.iter_mut() {
-        if rapier.intersection_pair(player, hurt) == Some(true) {
-            spike.add_death();
-            ev_reset_level.send(ResetLevel::Respawn);
+    for (hurt, hurt_marker, hurt_transform, spike) in q_hurt.iter_mut() {
+        if rapier.intersection_pair(hurtbox, hurt) != Some(true) {
+            continue;
+        }
+
+        let dies = match config.health_config.difficulty {
+            Difficulty::OneHit => true,
+            Difficulty::Health => health.damage(hurt_marker.damage),
+        };
+        if dies {
+            if let Some(mut spike) = spike {
+                spike.add_death();
+            }
+            ev_kill_player.send(KillPlayerEvent {
+                cause: DeathCause::Hazard,
+            });
             return;
         }
+
+        let away = player_transform.translation().x - hurt_transform.translation().x;
+        let direction = if away < 0. { -1. } else { 1. };
+        movement.knock_back(Vec2::new(KNOCKBACK_VEL.x * direction, KNOCKBACK_VEL.y));
+        commands.entity(player).insert(Invulnerable::from_seconds(
+            config.health_config.invulnerability_secs,
+        ));
+        return;
     }
 }
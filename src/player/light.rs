@@ -1,11 +1,13 @@
 use bevy::prelude::*;
-use bevy_rapier2d::{math::Real, plugin::RapierContext, prelude::CollisionGroups, prelude::*};
+use bevy_rapier2d::{math::Real, plugin::RapierContext};
 use enum_map::EnumMap;
 
 use crate::{
     input::CursorWorldCoords,
-    light::{LightColor, LightRaySource},
-    shared::GroupLabel,
+    light::{
+        segments::{cast_light_ray, trace_light_ray},
+        BlocksLight, LightColor, LightRaySource,
+    },
 };
 
 use super::PlayerMarker;
@@ -18,6 +20,12 @@ pub struct PlayerLightInventory {
     sources: EnumMap<LightColor, Option<Entity>>,
 }
 
+impl PlayerLightInventory {
+    pub fn current_color(&self) -> LightColor {
+        self.current_color
+    }
+}
+
 #[derive(Component)]
 pub struct AngleMarker;
 
@@ -123,18 +131,16 @@ pub fn shoot_light(
 }
 
 /// [`System`] that uses [`Gizmos`] to preview the light path while the left mouse button is held
-/// down. This system needs some work, namely:
-///
-/// - Not using [`Gizmos`] to render the light segments
-/// - Not copying the same code logic as
-///    [`simulate_light_sources`](crate::light::segments::simulate_light_sources).
+/// down, tracing it the same way as
+/// [`simulate_light_sources`](crate::light::segments::simulate_light_sources). This system still
+/// needs some work, as it shouldn't be using [`Gizmos`] to render the light segments.
 pub fn preview_light_path(
     mut q_rapier: Query<&mut RapierContext>,
     q_player: Query<(&Transform, &PlayerLightInventory), With<PlayerMarker>>,
     q_cursor: Query<&CursorWorldCoords>,
+    q_blocks_light: Query<(), With<BlocksLight>>,
     mut gizmos: Gizmos,
 ) {
-    // FIXME: duplicate code with some of light module, should be made common function
     let Ok(rapier_context) = q_rapier.get_single_mut() else {
         return;
     };
@@ -148,49 +154,34 @@ pub fn preview_light_path(
         return;
     }
 
-    let mut ray_pos = transform.translation.truncate();
-    let mut ray_dir = (cursor_pos.pos - ray_pos).normalize_or_zero();
+    let ray_pos = transform.translation.truncate();
+    let ray_dir = (cursor_pos.pos - ray_pos).normalize_or_zero();
 
     if ray_dir == Vec2::ZERO {
         return;
     }
 
-    let collision_groups = match inventory.current_color {
-        LightColor::White => CollisionGroups::new(
-            GroupLabel::WHITE_RAY,
-            GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR,
-        ),
-        LightColor::Blue => CollisionGroups::new(
-            GroupLabel::BLUE_RAY,
-            GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
-        ),
-        _ => CollisionGroups::new(
-            GroupLabel::LIGHT_RAY,
-            GroupLabel::TERRAIN | GroupLabel::LIGHT_SENSOR | GroupLabel::WHITE_RAY,
-        ),
-    };
-
-    let mut ray_qry = QueryFilter::new().groups(collision_groups);
-
-    for _ in 0..inventory.current_color.num_bounces() + 1 {
-        let Some((entity, intersection)) =
-            rapier_context.cast_ray_and_get_normal(ray_pos, ray_dir, Real::MAX, true, ray_qry)
-        else {
-            break;
-        };
-
-        if intersection.time_of_impact < 0.01 {
-            break;
-        }
-
-        gizmos.line_2d(
-            ray_pos,
-            intersection.point,
-            Color::from(inventory.current_color).darker(0.3),
-        );
+    let color = inventory.current_color;
+    let path = trace_light_ray(
+        ray_pos,
+        ray_dir,
+        color,
+        Real::MAX,
+        |ray_pos, ray_dir, max_distance, exclude| {
+            cast_light_ray(
+                &rapier_context,
+                color,
+                ray_pos,
+                ray_dir,
+                max_distance,
+                exclude,
+            )
+        },
+        |entity| q_blocks_light.contains(entity),
+    );
 
-        ray_pos = intersection.point;
-        ray_dir = ray_dir.reflect(intersection.normal);
-        ray_qry = ray_qry.exclude_collider(entity);
+    // only draw up to the last hit, a beam that hits nothing goes on forever
+    for segment in path.points.windows(2).take(path.hits.len()) {
+        gizmos.line_2d(segment[0], segment[1], Color::from(color).darker(0.3));
     }
 }
@@ -1,13 +1,20 @@
+use animation::{PlayerAnimation, PlayerAnimationPlugin};
 use bevy::{
     input::common_conditions::{input_just_pressed, input_just_released, input_pressed},
     prelude::*,
 };
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
+use dash::{fade_dash_afterimages, light_dashes, spawn_dash_afterimages};
+use health::PlayerHealthPlugin;
 use match_player::{
     post_update_match_player_pixel, pre_update_match_player_pixel, update_match_player_z,
 };
-use strand::{add_player_hair_and_cloth, update_player_strand_offsets, update_strand};
+use respawn::RespawnPlugin;
+use strand::{
+    add_player_hair_and_cloth, update_player_strand_offsets, update_strand, WindZoneBundle,
+};
+use strand_preset::StrandPresetPlugin;
 
 use crate::{
     input::update_cursor_world_coords,
@@ -19,22 +26,31 @@ use crate::{
     shared::{GameState, ResetLevel},
 };
 
-use kill::{kill_player_on_spike, reset_player_on_level_switch, reset_player_position};
+use kill::{hurt_player, reset_player_on_level_switch, reset_player_position};
 use light::{
     despawn_angle_indicator, handle_color_switch, preview_light_path, shoot_light,
     spawn_angle_indicator, PlayerLightInventory,
 };
 use movement::{
-    crouch_player, move_player, queue_jump, update_player_state, PlayerMovement, PlayerState,
+    crouch_player, move_player, queue_dash, queue_jump, update_player_facing, update_player_state,
+    Facing, PlayerMovement, PlayerState,
 };
-use spawn::{add_player_sensors, init_player_bundle, PlayerHurtMarker};
+use spawn::{add_player_sensors, init_player_bundle};
+
+pub use kill::{DeathCause, KillPlayerEvent};
+pub use spawn::PlayerHurtMarker;
 
+mod animation;
+mod dash;
+pub mod health;
 mod kill;
 pub mod light;
 pub mod match_player;
 pub mod movement;
+mod respawn;
 mod spawn;
 mod strand;
+pub mod strand_preset;
 
 /// [`Plugin`] for anything player based.
 pub struct PlayerManagementPlugin;
@@ -45,6 +61,11 @@ impl Plugin for PlayerManagementPlugin {
             PreUpdate,
             add_player_sensors.in_set(LevelSystems::Processing),
         )
+        .add_plugins(PlayerAnimationPlugin)
+        .add_plugins(PlayerHealthPlugin)
+        .add_plugins(RespawnPlugin)
+        .add_plugins(StrandPresetPlugin)
+        .register_ldtk_entity::<WindZoneBundle>("WindZone")
         .add_systems(
             FixedUpdate,
             move_player
@@ -58,6 +79,13 @@ impl Plugin for PlayerManagementPlugin {
                 .before(move_player)
                 .in_set(LevelSystems::Simulation),
         )
+        .add_systems(
+            Update,
+            queue_dash
+                .run_if(input_just_pressed(KeyCode::ShiftLeft))
+                .before(move_player)
+                .in_set(LevelSystems::Simulation),
+        )
         .add_systems(
             Update,
             crouch_player
@@ -88,10 +116,7 @@ impl Plugin for PlayerManagementPlugin {
                 .run_if(input_just_pressed(KeyCode::KeyR))
                 .run_if(in_state(GameState::Playing)),
         )
-        .add_systems(
-            Update,
-            kill_player_on_spike.in_set(LevelSystems::Simulation),
-        )
+        .add_systems(Update, hurt_player.in_set(LevelSystems::Simulation))
         .add_systems(Update, set_semisolid.in_set(LevelSystems::Simulation))
         .add_systems(
             Update,
@@ -111,7 +136,20 @@ impl Plugin for PlayerManagementPlugin {
         )
         .add_systems(
             FixedUpdate,
-            update_player_strand_offsets.in_set(LevelSystems::Simulation),
+            (update_player_facing, update_player_strand_offsets)
+                .chain()
+                .after(move_player)
+                .in_set(LevelSystems::Simulation),
+        )
+        .add_systems(
+            FixedUpdate,
+            (spawn_dash_afterimages, light_dashes)
+                .after(move_player)
+                .in_set(LevelSystems::Simulation),
+        )
+        .add_systems(
+            Update,
+            fade_dash_afterimages.in_set(LevelSystems::Simulation),
         );
     }
 }
@@ -133,6 +171,8 @@ pub struct PlayerBundle {
     restitution: Restitution,
     player_movement: PlayerMovement,
     player_state: PlayerState,
+    facing: Facing,
+    animation: PlayerAnimation,
     light_inventory: PlayerLightInventory,
     point_lighting: PointLighting,
 }
@@ -143,7 +183,7 @@ pub struct LdtkPlayerBundle {
     player_marker: PlayerMarker,
     #[with(init_player_bundle)]
     player: PlayerBundle,
-    #[sprite("lyra_bald.png")]
+    /// Set by [`animate_player`](animation::animate_player) once the spritesheet has loaded
     sprite: Sprite,
     #[worldly]
     worldly: Worldly,
@@ -152,6 +192,8 @@ pub struct LdtkPlayerBundle {
 }
 
 /// [`System`] that will cause a state switch to [`GameState::Respawning`] when the "R" key is pressed.
-fn quick_reset(mut ev_reset_level: EventWriter<ResetLevel>) {
-    ev_reset_level.send(ResetLevel::Respawn);
+fn quick_reset(mut ev_kill_player: EventWriter<KillPlayerEvent>) {
+    ev_kill_player.send(KillPlayerEvent {
+        cause: DeathCause::Reset,
+    });
 }
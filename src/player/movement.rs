@@ -1,25 +1,60 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-use super::{spawn::PlayerHurtMarker, PlayerMarker};
+use crate::{config::Config, shared::GroupLabel};
+
+use super::PlayerMarker;
 
 /// The number of [`FixedUpdate`] steps the player can jump for after pressing the spacebar.
 const SHOULD_JUMP_TICKS: isize = 8;
 /// The number of [`FixedUpdate`] steps the player can jump for after falling off an edge.
 const COYOTE_TIME_TICKS: isize = 5;
 /// The number of [`FixedUpdate`] steps the player should receive upward velocity for.
-const JUMP_BOOST_TICKS: isize = 2;
+pub const JUMP_BOOST_TICKS: isize = 2;
+/// The number of [`FixedUpdate`] steps the player can dash for after pressing shift.
+const SHOULD_DASH_TICKS: isize = 4;
+/// The number of [`FixedUpdate`] steps the player stays invulnerable for after a dash ends.
+const DASH_INVULNERABILITY_GRACE_TICKS: isize = 4;
+/// The number of [`FixedUpdate`] steps the player has no horizontal control for after being
+/// knocked back.
+const KNOCKBACK_TICKS: isize = 8;
+/// The horizontal velocity of a knockback is multiplied by this every step.
+const KNOCKBACK_DRAG: f32 = 0.85;
 
 /// Max player horizontal velocity.
 const PLAYER_MAX_H_VEL: f32 = 1.5;
-/// Max player vertical velocity.
+/// Max player horizontal velocity while crouching.
+const PLAYER_CROUCH_MAX_H_VEL: f32 = 0.6;
+/// Max player upward velocity.
 const PLAYER_MAX_Y_VEL: f32 = 5.;
+/// Max player downward velocity.
+const PLAYER_MAX_FALL_VEL: f32 = 4.;
+/// Max player downward velocity while fast falling.
+const PLAYER_MAX_FAST_FALL_VEL: f32 = 6.;
 /// The positive y velocity added to the player every jump boost tick.
 const PLAYER_JUMP_VEL: f32 = 2.2;
 /// The x velocity added to the player when A/D is held.
 const PLAYER_MOVE_VEL: f32 = 0.6;
 /// The y velocity subtracted from the player due to gravity.
 const PLAYER_GRAVITY: f32 = 0.15;
+/// Gravity is multiplied by this near the apex of a jump while jump is held, so the player hangs
+/// in the air for a bit.
+const APEX_HANG_GRAVITY_MULTIPLIER: f32 = 0.5;
+/// The player is at the apex of their jump while their vertical speed is below this.
+const APEX_HANG_THRESHOLD: f32 = 0.6;
+/// Gravity is multiplied by this while falling.
+const FALL_GRAVITY_MULTIPLIER: f32 = 1.5;
+/// Gravity is multiplied by this while moving upwards with jump released, cutting the jump short.
+const JUMP_CUT_GRAVITY_MULTIPLIER: f32 = 4.0;
+/// Gravity is multiplied by this while falling with S held.
+const FAST_FALL_GRAVITY_MULTIPLIER: f32 = 2.5;
+
+/// Half of the width of the player's [`Collider`].
+pub const PLAYER_HALF_WIDTH: f32 = 6.0;
+/// Half of the height of the player's [`Collider`] while standing.
+pub const PLAYER_HALF_HEIGHT: f32 = 9.0;
+/// Half of the height of the player's [`Collider`] while crouching.
+pub const PLAYER_CROUCH_HALF_HEIGHT: f32 = 6.0;
 
 /// [`Component`] that stores information about the player's movement state.
 #[derive(Component, Default)]
@@ -30,6 +65,60 @@ pub struct PlayerMovement {
     should_jump_ticks_remaining: isize,
     coyote_time_ticks_remaining: isize,
     jump_boost_ticks_remaining: isize,
+    should_dash_ticks_remaining: isize,
+    dash_ticks_remaining: isize,
+    dash_cooldown_ticks_remaining: isize,
+    dash_invulnerability_ticks_remaining: isize,
+    /// The sign of the x axis in the direction of the current dash
+    dash_direction: f32,
+    /// Whether the player can dash while in the air, reset every time the player lands
+    can_air_dash: bool,
+    knockback_ticks_remaining: isize,
+}
+
+impl PlayerMovement {
+    pub fn is_dashing(&self) -> bool {
+        self.dash_ticks_remaining > 0
+    }
+
+    /// Whether the player should ignore [`HurtMarker`](crate::level::entity::HurtMarker)s, which
+    /// is the case during a dash and for a few steps after it.
+    pub fn is_invulnerable(&self) -> bool {
+        self.dash_invulnerability_ticks_remaining > 0
+    }
+
+    pub fn is_knocked_back(&self) -> bool {
+        self.knockback_ticks_remaining > 0
+    }
+
+    /// Sends the player flying with the given velocity, cancelling any jump or dash. The player
+    /// can't move horizontally until the knockback is over.
+    pub fn knock_back(&mut self, velocity: Vec2) {
+        self.velocity = velocity;
+        self.knockback_ticks_remaining = KNOCKBACK_TICKS;
+        self.jump_boost_ticks_remaining = 0;
+        self.should_jump_ticks_remaining = 0;
+        self.dash_ticks_remaining = 0;
+    }
+}
+
+/// [`Component`] that stores the horizontal direction the player is facing. The player sprite is
+/// drawn facing right, and is flipped when facing left.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Facing {
+    /// The sign of the x axis in the direction the player is facing.
+    pub fn sign(&self) -> f32 {
+        match self {
+            Facing::Left => -1.0,
+            Facing::Right => 1.0,
+        }
+    }
 }
 
 #[derive(Component, Default, PartialEq, Eq)]
@@ -39,6 +128,7 @@ pub enum PlayerState {
     Jumping,
     Falling,
     Moving,
+    Dashing,
 }
 
 /// [`System`] that is run the frame the space bar is pressed. Allows the player to jump for the
@@ -50,47 +140,85 @@ pub fn queue_jump(mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>)
     player.should_jump_ticks_remaining = SHOULD_JUMP_TICKS;
 }
 
-/// [`System`] that is run on [`Update`] to crouch player
+/// [`System`] that is run the frame shift is pressed. Allows the player to dash for the next couple
+/// of frames, if the dash is off cooldown.
+pub fn queue_dash(mut q_player: Query<&mut PlayerMovement, With<PlayerMarker>>) {
+    let Ok(mut player) = q_player.get_single_mut() else {
+        return;
+    };
+    player.should_dash_ticks_remaining = SHOULD_DASH_TICKS;
+}
+
+/// [`System`] that is run on [`Update`] to crouch the player while S is held on the ground. In the
+/// air, S fast falls instead. Crouching swaps the player's [`Collider`] for a shorter one (keeping
+/// the player's feet in place), and the player will stay crouched after S is released until there
+/// is enough room above them to stand up.
 pub fn crouch_player(
-    // query transform
-    mut q_player: Query<(&mut PlayerMovement, &mut Transform), With<PlayerMarker>>,
-    mut q_hitbox: Query<&mut Transform, (With<PlayerHurtMarker>, Without<PlayerMarker>)>,
-    //ButtonInput<KeyCode> resource (access resource)
+    mut q_player: Query<
+        (
+            &mut PlayerMovement,
+            &mut Transform,
+            &mut Collider,
+            &KinematicCharacterControllerOutput,
+        ),
+        With<PlayerMarker>,
+    >,
+    rapier_context: ReadDefaultRapierContext,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
-    // ensure only 1 candidate to match query; let Ok = pattern matching
-    let Ok((mut player, mut transform)) = q_player.get_single_mut() else {
-        return;
-    };
-    let Ok(mut hitbox_transform) = q_hitbox.get_single_mut() else {
+    let Ok((mut player, mut transform, mut collider, output)) = q_player.get_single_mut() else {
         return;
     };
-    hitbox_transform.translation = Vec3::new(0., 0., 0.);
 
-    if keys.just_pressed(KeyCode::KeyS) && !player.crouching {
-        // decrease size by half
-        transform.scale.y *= 0.5;
-        transform.translation.y -= 5.0;
+    // the difference in height between the centers of the standing and crouching colliders
+    let crouch_offset = PLAYER_HALF_HEIGHT - PLAYER_CROUCH_HALF_HEIGHT;
+
+    if keys.pressed(KeyCode::KeyS) && !player.crouching && output.grounded {
+        *collider = Collider::cuboid(PLAYER_HALF_WIDTH, PLAYER_CROUCH_HALF_HEIGHT);
+        transform.translation.y -= crouch_offset;
         player.crouching = true;
     }
-    if keys.just_released(KeyCode::KeyS) && player.crouching {
-        transform.scale.y *= 2.0;
-        transform.translation.y += 5.0;
+    if !keys.pressed(KeyCode::KeyS) && player.crouching {
+        // cast the crouching collider up to where the top of the standing collider would be, and
+        // refuse to stand up if it hits anything
+        let ceiling = rapier_context.cast_shape(
+            transform.translation.truncate(),
+            0.0,
+            Vec2::Y,
+            &collider,
+            ShapeCastOptions::with_max_time_of_impact(crouch_offset * 2.0),
+            QueryFilter::new().groups(CollisionGroups::new(
+                GroupLabel::PLAYER_COLLIDER,
+                GroupLabel::TERRAIN,
+            )),
+        );
+        if ceiling.is_some() {
+            return;
+        }
+
+        *collider = Collider::cuboid(PLAYER_HALF_WIDTH, PLAYER_HALF_HEIGHT);
+        transform.translation.y += crouch_offset;
         player.crouching = false;
     }
 }
 
 pub fn update_player_state(
     mut q_player: Query<
-        (&mut PlayerState, &KinematicCharacterControllerOutput),
+        (
+            &mut PlayerState,
+            &PlayerMovement,
+            &KinematicCharacterControllerOutput,
+        ),
         With<PlayerMarker>,
     >,
 ) {
-    let Ok((mut state, output)) = q_player.get_single_mut() else {
+    let Ok((mut state, movement, output)) = q_player.get_single_mut() else {
         return;
     };
 
-    if output.effective_translation.y > 0.0 {
+    if movement.is_dashing() {
+        *state = PlayerState::Dashing;
+    } else if output.effective_translation.y > 0.0 {
         *state = PlayerState::Jumping;
     } else if output.effective_translation.y <= 0.0 && !output.grounded {
         *state = PlayerState::Falling;
@@ -101,6 +229,72 @@ pub fn update_player_state(
     }
 }
 
+/// [`System`] that updates the player's [`Facing`] from the horizontal input, falling back to the
+/// player's horizontal velocity, and flips the player [`Sprite`] to match.
+pub fn update_player_facing(
+    mut q_player: Query<(&PlayerMovement, &mut Facing, &mut Sprite), With<PlayerMarker>>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    let Ok((player, mut facing, mut sprite)) = q_player.get_single_mut() else {
+        return;
+    };
+
+    let new_facing = match (keys.pressed(KeyCode::KeyA), keys.pressed(KeyCode::KeyD)) {
+        (true, false) => Some(Facing::Left),
+        (false, true) => Some(Facing::Right),
+        _ if player.velocity.x < 0. => Some(Facing::Left),
+        _ if player.velocity.x > 0. => Some(Facing::Right),
+        _ => None,
+    };
+    if let Some(new_facing) = new_facing {
+        facing.set_if_neq(new_facing);
+    }
+
+    let flip_x = *facing == Facing::Left;
+    if sprite.flip_x != flip_x {
+        sprite.flip_x = flip_x;
+    }
+}
+
+/// The inputs that affect the player's vertical velocity over one [`FixedUpdate`] step.
+#[derive(Default, Clone, Copy)]
+pub struct VerticalInput {
+    /// Whether the player is in the boost phase of a jump
+    pub jump_boost: bool,
+    pub jump_held: bool,
+    /// Whether the player is holding S in the air
+    pub fast_fall: bool,
+}
+
+/// Computes the player's vertical velocity after one [`FixedUpdate`] step. Gravity is scaled
+/// depending on where the player is in their jump: reduced near the apex while jump is held,
+/// increased while falling, and increased further when jump is released early or the player is
+/// fast falling.
+pub fn next_vertical_velocity(velocity_y: f32, input: VerticalInput) -> f32 {
+    if input.jump_boost {
+        return PLAYER_JUMP_VEL;
+    }
+
+    let gravity_multiplier = if input.fast_fall && velocity_y <= 0. {
+        FAST_FALL_GRAVITY_MULTIPLIER
+    } else if velocity_y > 0. && !input.jump_held {
+        JUMP_CUT_GRAVITY_MULTIPLIER
+    } else if input.jump_held && velocity_y.abs() < APEX_HANG_THRESHOLD {
+        APEX_HANG_GRAVITY_MULTIPLIER
+    } else if velocity_y <= 0. {
+        FALL_GRAVITY_MULTIPLIER
+    } else {
+        1.
+    };
+    let max_fall_vel = if input.fast_fall {
+        PLAYER_MAX_FAST_FALL_VEL
+    } else {
+        PLAYER_MAX_FALL_VEL
+    };
+
+    (velocity_y - PLAYER_GRAVITY * gravity_multiplier).clamp(-max_fall_vel, PLAYER_MAX_Y_VEL)
+}
+
 /// [`System`] that is run on [`Update`] to move the player around.
 pub fn move_player(
     mut q_player: Query<
@@ -108,17 +302,63 @@ pub fn move_player(
             &mut KinematicCharacterController,
             &KinematicCharacterControllerOutput,
             &mut PlayerMovement,
+            &Facing,
         ),
         With<PlayerMarker>,
     >,
     keys: Res<ButtonInput<KeyCode>>,
+    config: Res<Config>,
 ) {
-    let Ok((mut controller, output, mut player)) = q_player.get_single_mut() else {
+    let Ok((mut controller, output, mut player, facing)) = q_player.get_single_mut() else {
         return;
     };
 
     if output.grounded {
         player.coyote_time_ticks_remaining = COYOTE_TIME_TICKS;
+        player.can_air_dash = true;
+    }
+
+    let dash = &config.dash_config;
+    if player.should_dash_ticks_remaining > 0
+        && player.dash_cooldown_ticks_remaining <= 0
+        && !player.crouching
+        && !player.is_knocked_back()
+        && (output.grounded || player.can_air_dash)
+    {
+        if !output.grounded {
+            player.can_air_dash = false;
+        }
+        player.dash_direction = match (keys.pressed(KeyCode::KeyA), keys.pressed(KeyCode::KeyD)) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => facing.sign(),
+        };
+        player.dash_ticks_remaining = dash.duration_ticks as isize;
+        player.dash_cooldown_ticks_remaining = (dash.duration_ticks + dash.cooldown_ticks) as isize;
+        player.dash_invulnerability_ticks_remaining =
+            dash.duration_ticks as isize + DASH_INVULNERABILITY_GRACE_TICKS;
+        player.should_dash_ticks_remaining = 0;
+        player.jump_boost_ticks_remaining = 0;
+    }
+
+    player.should_dash_ticks_remaining -= 1;
+    player.dash_cooldown_ticks_remaining -= 1;
+    player.dash_invulnerability_ticks_remaining -= 1;
+
+    // dashing overrides the rest of the velocity model: the player moves in a straight horizontal
+    // line, ignoring gravity
+    if player.is_dashing() {
+        player.dash_ticks_remaining -= 1;
+        let dash_speed = dash.distance / dash.duration_ticks.max(1) as f32;
+        player.velocity = Vec2::new(player.dash_direction * dash_speed, 0.);
+        if !player.is_dashing() {
+            // don't carry the dash's speed out of the dash
+            player.velocity.x = player.velocity.x.clamp(-PLAYER_MAX_H_VEL, PLAYER_MAX_H_VEL);
+        }
+        player.should_jump_ticks_remaining -= 1;
+        player.coyote_time_ticks_remaining -= 1;
+        controller.translation = Some(player.velocity);
+        return;
     }
 
     // Can only jump if they've pressed space within the past SHOULD_JUMP_TICKS, and they have been
@@ -126,41 +366,54 @@ pub fn move_player(
     if player.should_jump_ticks_remaining > 0 && player.coyote_time_ticks_remaining > 0 {
         player.jump_boost_ticks_remaining = JUMP_BOOST_TICKS;
     } else if !keys.pressed(KeyCode::Space) && player.velocity.y > 0. {
-        // Jump was cut
-        player.velocity.y = PLAYER_GRAVITY;
+        // Jump was cut, the rest is handled by the increased gravity in `next_vertical_velocity`
         player.jump_boost_ticks_remaining = 0;
     } else if output.desired_translation.y > 0. && output.effective_translation.y < 0.05 {
         // Bonked head onto wall
         player.velocity.y = 0.;
         player.jump_boost_ticks_remaining = 0;
-    } else if output.grounded {
+    } else if output.grounded && !(player.is_knocked_back() && player.velocity.y > 0.) {
+        // don't cancel the upward velocity of a knockback that started on the ground
         player.velocity.y = 0.;
     }
 
-    if player.jump_boost_ticks_remaining > 0 {
-        player.velocity.y = PLAYER_JUMP_VEL;
-    } else {
-        player.velocity.y -= PLAYER_GRAVITY;
-    }
-
-    player.velocity.y = player.velocity.y.clamp(-PLAYER_MAX_Y_VEL, PLAYER_MAX_Y_VEL);
+    player.velocity.y = next_vertical_velocity(
+        player.velocity.y,
+        VerticalInput {
+            jump_boost: player.jump_boost_ticks_remaining > 0,
+            // knockbacks shouldn't be cut short like a released jump
+            jump_held: keys.pressed(KeyCode::Space) || player.is_knocked_back(),
+            fast_fall: keys.pressed(KeyCode::KeyS) && !output.grounded,
+        },
+    );
 
-    let mut moved = false;
-    if keys.pressed(KeyCode::KeyA) {
-        player.velocity.x -= PLAYER_MOVE_VEL;
-        moved = true;
-    }
-    if keys.pressed(KeyCode::KeyD) {
-        player.velocity.x += PLAYER_MOVE_VEL;
-        moved = true;
-    }
-    player.velocity.x = player.velocity.x.clamp(-PLAYER_MAX_H_VEL, PLAYER_MAX_H_VEL);
-    if !moved {
-        // slow player down when not moving horizontally
-        // NOTE: why not using rapier friction?
-        player.velocity.x *= 0.6;
-        if player.velocity.x.abs() < 0.1 {
-            player.velocity.x = 0.;
+    if player.is_knocked_back() {
+        // the player has no control while being knocked back, and slowly loses speed instead
+        player.velocity.x *= KNOCKBACK_DRAG;
+        player.knockback_ticks_remaining -= 1;
+    } else {
+        let mut moved = false;
+        if keys.pressed(KeyCode::KeyA) {
+            player.velocity.x -= PLAYER_MOVE_VEL;
+            moved = true;
+        }
+        if keys.pressed(KeyCode::KeyD) {
+            player.velocity.x += PLAYER_MOVE_VEL;
+            moved = true;
+        }
+        let max_h_vel = if player.crouching {
+            PLAYER_CROUCH_MAX_H_VEL
+        } else {
+            PLAYER_MAX_H_VEL
+        };
+        player.velocity.x = player.velocity.x.clamp(-max_h_vel, max_h_vel);
+        if !moved {
+            // slow player down when not moving horizontally
+            // NOTE: why not using rapier friction?
+            player.velocity.x *= 0.6;
+            if player.velocity.x.abs() < 0.1 {
+                player.velocity.x = 0.;
+            }
         }
     }
 
@@ -170,3 +423,129 @@ pub fn move_player(
 
     controller.translation = Some(player.velocity);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Simulates a jump from the ground with jump held for the first `held_ticks` steps, returning
+    /// the highest point reached and the number of steps until the player lands again.
+    fn simulate_jump(held_ticks: isize) -> (f32, isize) {
+        let mut velocity_y = 0.;
+        let mut height = 0.;
+        let mut max_height: f32 = 0.;
+        let mut tick = 0;
+        loop {
+            velocity_y = next_vertical_velocity(
+                velocity_y,
+                VerticalInput {
+                    jump_boost: tick < JUMP_BOOST_TICKS,
+                    jump_held: tick < held_ticks,
+                    fast_fall: false,
+                },
+            );
+            height += velocity_y;
+            max_height = max_height.max(height);
+            tick += 1;
+            if height <= 0. {
+                return (max_height, tick);
+            }
+        }
+    }
+
+    /// Simulates falling from rest for `ticks` steps, returning the final velocity.
+    fn simulate_fall(ticks: usize, fast_fall: bool) -> f32 {
+        (0..ticks).fold(0., |velocity_y, _| {
+            next_vertical_velocity(
+                velocity_y,
+                VerticalInput {
+                    fast_fall,
+                    ..default()
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn full_jump_height() {
+        // a full jump should clear a bit over two tiles
+        let (height, _) = simulate_jump(isize::MAX);
+        assert!(
+            (18. ..24.).contains(&height),
+            "full jump height was {height}"
+        );
+    }
+
+    #[test]
+    fn short_hop_height() {
+        let (height, _) = simulate_jump(1);
+        assert!(
+            (4. ..10.).contains(&height),
+            "short hop height was {height}"
+        );
+    }
+
+    #[test]
+    fn holding_jump_longer_jumps_higher() {
+        let heights: Vec<f32> = [1, 4, 8, 12, 16]
+            .into_iter()
+            .map(|held_ticks| simulate_jump(held_ticks).0)
+            .collect();
+        assert!(
+            heights.windows(2).all(|pair| pair[0] < pair[1]),
+            "jump heights were {heights:?}"
+        );
+    }
+
+    #[test]
+    fn apex_hang_reduces_gravity_while_jump_is_held() {
+        let velocity_y = APEX_HANG_THRESHOLD / 2.;
+        let held = next_vertical_velocity(
+            velocity_y,
+            VerticalInput {
+                jump_held: true,
+                ..default()
+            },
+        );
+        assert_eq!(
+            held,
+            velocity_y - PLAYER_GRAVITY * APEX_HANG_GRAVITY_MULTIPLIER
+        );
+
+        let released = next_vertical_velocity(velocity_y, VerticalInput::default());
+        assert!(released < held);
+    }
+
+    #[test]
+    fn falling_is_faster_than_rising() {
+        let (_, full_jump_ticks) = simulate_jump(isize::MAX);
+        let rising_ticks = {
+            let mut velocity_y = 0.;
+            let mut tick = 0;
+            while tick < JUMP_BOOST_TICKS || velocity_y > 0. {
+                velocity_y = next_vertical_velocity(
+                    velocity_y,
+                    VerticalInput {
+                        jump_boost: tick < JUMP_BOOST_TICKS,
+                        jump_held: true,
+                        fast_fall: false,
+                    },
+                );
+                tick += 1;
+            }
+            tick
+        };
+        assert!(full_jump_ticks - rising_ticks < rising_ticks);
+    }
+
+    #[test]
+    fn fall_speed_is_capped() {
+        assert_eq!(simulate_fall(200, false), -PLAYER_MAX_FALL_VEL);
+        assert_eq!(simulate_fall(200, true), -PLAYER_MAX_FAST_FALL_VEL);
+    }
+
+    #[test]
+    fn fast_fall_accelerates_faster() {
+        assert!(simulate_fall(5, true) < simulate_fall(5, false));
+    }
+}
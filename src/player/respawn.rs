@@ -0,0 +1,222 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{
+    level::LevelSystems,
+    shared::{GameState, ResetLevel, RespawnState},
+};
+
+use super::{kill::KillPlayerEvent, strand::Strand, PlayerMarker};
+
+const DYING_SECS: f32 = 0.5;
+const FADE_OUT_SECS: f32 = 0.3;
+const FADE_IN_SECS: f32 = 0.3;
+
+const DEATH_PARTICLE_COUNT: usize = 12;
+/// Speed of the death particles in pixels per second
+const DEATH_PARTICLE_SPEED: f32 = 60.0;
+/// Acceleration of the death particles due to gravity in pixels per second squared
+const DEATH_PARTICLE_GRAVITY: f32 = 150.0;
+
+/// [`Plugin`] that plays the death sequence when a [`KillPlayerEvent`] is sent: the player bursts
+/// into particles, the screen fades out, the level is reset with [`ResetLevel::Respawn`], and the
+/// screen fades back in. The sequence is driven by the [`RespawnState`] sub-state, so everything
+/// in [`LevelSystems::Simulation`] (including player input) is frozen while it plays.
+pub struct RespawnPlugin;
+
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<KillPlayerEvent>()
+            .init_resource::<RespawnTimer>()
+            .add_systems(
+                Update,
+                start_death_sequence
+                    .run_if(on_event::<KillPlayerEvent>)
+                    .run_if(in_state(GameState::Playing))
+                    .after(LevelSystems::Simulation),
+            )
+            .add_systems(
+                OnEnter(GameState::Respawning),
+                (hide_player, spawn_death_particles, spawn_fade_overlay),
+            )
+            .add_systems(OnExit(GameState::Respawning), cleanup_death_sequence)
+            .add_systems(OnEnter(RespawnState::Dying), reset_respawn_timer)
+            .add_systems(OnEnter(RespawnState::FadeOut), reset_respawn_timer)
+            .add_systems(
+                OnEnter(RespawnState::FadeIn),
+                (reset_respawn_timer, respawn_player),
+            )
+            .add_systems(
+                Update,
+                (
+                    advance_respawn_state,
+                    update_death_particles,
+                    update_fade_overlay,
+                )
+                    .run_if(in_state(GameState::Respawning)),
+            );
+    }
+}
+
+/// [`Resource`] that times the current stage of the death sequence.
+#[derive(Resource, Default)]
+struct RespawnTimer(Timer);
+
+/// Marker [`Component`] for the full screen [`Node`] used to fade the screen out and back in.
+#[derive(Component)]
+struct FadeOverlay;
+
+/// [`Component`] for the particles the player bursts into when they die.
+#[derive(Component)]
+struct DeathParticle {
+    velocity: Vec2,
+}
+
+fn start_death_sequence(
+    mut ev_kill_player: EventReader<KillPlayerEvent>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    for ev in ev_kill_player.read() {
+        debug!("Lyra died: {:?}", ev.cause);
+    }
+    next_game_state.set(GameState::Respawning);
+}
+
+fn reset_respawn_timer(state: Res<State<RespawnState>>, mut timer: ResMut<RespawnTimer>) {
+    let secs = match state.get() {
+        RespawnState::Dying => DYING_SECS,
+        RespawnState::FadeOut => FADE_OUT_SECS,
+        RespawnState::FadeIn => FADE_IN_SECS,
+    };
+    timer.0 = Timer::from_seconds(secs, TimerMode::Once);
+}
+
+/// [`System`] that moves on to the next stage of the death sequence once the current one is over,
+/// returning to [`GameState::Playing`] after the screen has faded back in.
+fn advance_respawn_state(
+    state: Res<State<RespawnState>>,
+    mut next_respawn_state: ResMut<NextState<RespawnState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut timer: ResMut<RespawnTimer>,
+    time: Res<Time>,
+) {
+    timer.0.tick(time.delta());
+    if !timer.0.finished() {
+        return;
+    }
+    match state.get() {
+        RespawnState::Dying => next_respawn_state.set(RespawnState::FadeOut),
+        RespawnState::FadeOut => next_respawn_state.set(RespawnState::FadeIn),
+        RespawnState::FadeIn => next_game_state.set(GameState::Playing),
+    }
+}
+
+/// [`System`] that resets the level while the screen is black, moving the player back to the start.
+fn respawn_player(mut ev_reset_level: EventWriter<ResetLevel>) {
+    ev_reset_level.send(ResetLevel::Respawn);
+}
+
+/// [`System`] that hides the player and their hair and cloth so that only the death particles are
+/// visible. Hiding the player also hides the light around them.
+fn hide_player(
+    mut commands: Commands,
+    mut q_player: Query<&mut Visibility, With<PlayerMarker>>,
+    q_strands: Query<Entity, With<Strand>>,
+) {
+    let Ok(mut visibility) = q_player.get_single_mut() else {
+        return;
+    };
+    *visibility = Visibility::Hidden;
+    for strand in q_strands.iter() {
+        commands.entity(strand).insert(Visibility::Hidden);
+    }
+}
+
+fn spawn_death_particles(mut commands: Commands, q_player: Query<&Transform, With<PlayerMarker>>) {
+    let Ok(player_transform) = q_player.get_single() else {
+        return;
+    };
+    for i in 0..DEATH_PARTICLE_COUNT {
+        let angle = i as f32 / DEATH_PARTICLE_COUNT as f32 * TAU;
+        // alternate between fast and slow particles so the burst doesn't look like a perfect ring
+        let speed = DEATH_PARTICLE_SPEED * if i % 2 == 0 { 1.0 } else { 0.6 };
+        commands.spawn((
+            DeathParticle {
+                velocity: Vec2::from_angle(angle) * speed,
+            },
+            Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::splat(2.0)),
+                ..default()
+            },
+            Transform::from_translation(player_transform.translation + Vec3::Z),
+        ));
+    }
+}
+
+fn update_death_particles(
+    mut q_particles: Query<(&mut DeathParticle, &mut Transform, &mut Sprite)>,
+    state: Res<State<RespawnState>>,
+    timer: Res<RespawnTimer>,
+    time: Res<Time>,
+) {
+    // particles fade away over the course of the dying stage
+    let alpha = match state.get() {
+        RespawnState::Dying => 1.0 - timer.0.fraction(),
+        _ => 0.0,
+    };
+    for (mut particle, mut transform, mut sprite) in q_particles.iter_mut() {
+        particle.velocity.y -= DEATH_PARTICLE_GRAVITY * time.delta_secs();
+        transform.translation += particle.velocity.extend(0.0) * time.delta_secs();
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+fn spawn_fade_overlay(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.0)),
+        GlobalZIndex(i32::MAX),
+        FadeOverlay,
+    ));
+}
+
+fn update_fade_overlay(
+    mut q_overlay: Query<&mut BackgroundColor, With<FadeOverlay>>,
+    state: Res<State<RespawnState>>,
+    timer: Res<RespawnTimer>,
+) {
+    let Ok(mut background) = q_overlay.get_single_mut() else {
+        return;
+    };
+    let alpha = match state.get() {
+        RespawnState::Dying => 0.0,
+        RespawnState::FadeOut => timer.0.fraction(),
+        RespawnState::FadeIn => 1.0 - timer.0.fraction(),
+    };
+    background.0.set_alpha(alpha);
+}
+
+/// [`System`] that removes the death particles and fade overlay, and shows the player again.
+fn cleanup_death_sequence(
+    mut commands: Commands,
+    mut q_player: Query<&mut Visibility, With<PlayerMarker>>,
+    q_strands: Query<Entity, With<Strand>>,
+    q_particles: Query<Entity, With<DeathParticle>>,
+    q_overlay: Query<Entity, With<FadeOverlay>>,
+) {
+    for entity in q_particles.iter().chain(q_overlay.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Ok(mut visibility) = q_player.get_single_mut() {
+        *visibility = Visibility::Inherited;
+    }
+    for strand in q_strands.iter() {
+        commands.entity(strand).insert(Visibility::Inherited);
+    }
+}
@@ -5,8 +5,9 @@ use bevy_rapier2d::prelude::*;
 use crate::{lighting::light::PointLighting, shared::GroupLabel};
 
 use super::{
+    animation::PlayerAnimation,
     light::PlayerLightInventory,
-    movement::{PlayerMovement, PlayerState},
+    movement::{Facing, PlayerMovement, PlayerState, PLAYER_HALF_HEIGHT, PLAYER_HALF_WIDTH},
     PlayerBundle, PlayerMarker,
 };
 
@@ -24,13 +25,17 @@ pub fn init_player_bundle(_: &EntityInstance) -> PlayerBundle {
                 GroupLabel::TERRAIN,
             )),
             offset: CharacterLength::Absolute(1.0),
+            // push dynamic bodies like push blocks out of the way
+            apply_impulse_to_dynamic_bodies: true,
             ..default()
         },
         controller_output: KinematicCharacterControllerOutput::default(),
-        collider: Collider::cuboid(6.0, 9.0),
+        collider: Collider::cuboid(PLAYER_HALF_WIDTH, PLAYER_HALF_HEIGHT),
         collision_groups: CollisionGroups::new(GroupLabel::PLAYER_COLLIDER, GroupLabel::TERRAIN),
         player_movement: PlayerMovement::default(),
         player_state: PlayerState::Idle,
+        facing: Facing::Right,
+        animation: PlayerAnimation::default(),
         friction: Friction {
             coefficient: 0.,
             combine_rule: CoefficientCombineRule::Min,
@@ -62,7 +67,7 @@ pub fn add_player_sensors(mut commands: Commands, q_player: Query<Entity, Added<
             .insert(PlayerHurtMarker)
             .insert(CollisionGroups::new(
                 GroupLabel::PLAYER_SENSOR,
-                GroupLabel::HURT_BOX | GroupLabel::TERRAIN,
+                GroupLabel::HURT_BOX | GroupLabel::TERRAIN | GroupLabel::TRIGGER,
             ))
             .insert(PointLight {
                 intensity: 100_000.0,
@@ -1,11 +1,29 @@
-use std::ops::Range;
-
 use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    level::error::LevelDataCheck, player::match_player::MatchPlayerPixel, shared::GroupLabel,
+};
 
-use crate::{player::match_player::MatchPlayerPixel, shared::GroupLabel};
+use super::{
+    match_player::MatchPlayerZ,
+    movement::{Facing, PlayerMovement, PlayerState},
+    strand_preset::{AttachedStrands, StrandChain},
+    PlayerMarker,
+};
 
-use super::{match_player::MatchPlayerZ, PlayerMarker};
+/// The number of times the distance constraints between strands are solved every [`FixedUpdate`].
+/// More iterations make chains of strands stretch less.
+const STRAND_SOLVER_ITERATIONS: usize = 4;
+/// The radius of the circle that is cast against terrain when moving strands with `physics`.
+const STRAND_RADIUS: f32 = 1.0;
+/// How far strands stop short of the terrain they hit, so they don't get stuck inside of it.
+const STRAND_SKIN: f32 = 0.01;
+/// Strands further than this from where they should be (e.g. when the player respawns) are moved
+/// straight there instead of being simulated.
+const STRAND_SNAP_DISTANCE: f32 = 32.0;
 
 #[derive(Component)]
 /// [`Component`] representing one node in a chain of strands, used to simulate hair and clothes.
@@ -19,13 +37,17 @@ pub struct Strand {
 
     /// Acceleration due to gravity, applied every [`FixedUpdate`]
     pub gravity: f32,
-    /// The strand's velocity is multiplied by `friction` before being added to the [`Transform`] every [`FixedUpdate`]
+    /// The strand's velocity is multiplied by `friction` before being added to the [`Transform`] every [`FixedUpdate`],
+    /// so lower values dampen the strand's movement more
     pub friction: f32,
+    /// The fraction of the distance to `dist` away from `connect` the strand is moved every solver
+    /// iteration, from 0 to 1. Lower values make the strand more elastic.
+    pub stiffness: f32,
     /// Specifies update order, with lower numbers updated first. Usually, strands nearer to the source (e.g. the player)
     /// should have a lower `priority` value.
     pub priority: u32,
 
-    /// Specifies whether the strand collides with the ground
+    /// Specifies whether the strand collides with terrain
     pub physics: bool,
 
     last_pos: Vec2,
@@ -38,6 +60,7 @@ impl Strand {
         dist: f32,
         gravity: f32,
         friction: f32,
+        stiffness: f32,
         priority: u32,
         physics: bool,
     ) -> Self {
@@ -47,6 +70,7 @@ impl Strand {
             dist,
             gravity,
             friction,
+            stiffness,
             priority,
             last_pos: Vec2::new(0.0, 0.0),
             physics,
@@ -54,68 +78,201 @@ impl Strand {
     }
 }
 
+/// [`Component`] for an area that pushes [`Strand`]s inside of it, e.g. to make hair and clothes
+/// blow in the wind. The area is centered on the entity's [`Transform`].
+#[derive(Component, Debug, Default, Clone)]
+#[require(Transform)]
+pub struct WindZone {
+    pub half_extents: Vec2,
+    /// Acceleration applied to strands in the zone every [`FixedUpdate`]
+    pub force: Vec2,
+}
+
+impl From<&EntityInstance> for WindZone {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        // missing fields are reported by the wind zone's `LevelDataCheck`
+        let force = Vec2::new(
+            *entity_instance.get_float_field("force_x").unwrap_or(&0.0),
+            *entity_instance.get_float_field("force_y").unwrap_or(&0.0),
+        );
+        WindZone {
+            half_extents: Vec2::new(
+                entity_instance.width as f32 / 2.0,
+                entity_instance.height as f32 / 2.0,
+            ),
+            force,
+        }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk to spawn [`WindZone`]s.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct WindZoneBundle {
+    #[from_entity_instance]
+    wind_zone: WindZone,
+    #[from_entity_instance]
+    check: LevelDataCheck,
+}
+
+/// [`System`] that simulates [`Strand`]s with verlet integration. Every [`FixedUpdate`], each
+/// strand keeps moving with its previous velocity (damped by `friction`) and is accelerated by
+/// gravity and any [`WindZone`]s. As strands are connected to the player, the player's movement
+/// carries over into their velocity, so hair and cloth trail behind and swing forward when the
+/// player stops. The distance constraints between strands are then solved over
+/// [`STRAND_SOLVER_ITERATIONS`] iterations. Strands with `physics` are swept against terrain
+/// with a circle cast whenever they move, so they slide along walls, floors and ceilings
+/// instead of passing through them.
 pub fn update_strand(
     mut q_strand: Query<(Entity, &mut Strand)>,
     mut q_transforms: Query<&mut Transform>,
+    q_parents: Query<&Parent>,
+    q_global_transforms: Query<&GlobalTransform>,
+    q_wind_zones: Query<(&WindZone, &GlobalTransform)>,
     rapier_context: ReadDefaultRapierContext,
 ) {
     let mut strands = q_strand.iter_mut().collect::<Vec<_>>();
     strands.sort_by(|(_, a), (_, b)| a.priority.cmp(&b.priority));
+    let ball = Collider::ball(STRAND_RADIUS);
+
     for (entity, strand) in strands.iter_mut() {
-        let Ok([mut transform, connect_transform]) =
-            q_transforms.get_many_mut([*entity, strand.connect])
-        else {
+        let Some(connect_pos) = connect_position(
+            strand.connect,
+            &q_transforms,
+            &q_parents,
+            &q_global_transforms,
+        ) else {
+            continue;
+        };
+        let connect_pos = connect_pos + strand.offset;
+        let Ok(mut transform) = q_transforms.get_mut(*entity) else {
             continue;
         };
-        let connect_pos = connect_transform.translation.truncate() + strand.offset;
         let mut pos = transform.translation.truncate();
 
-        let velocity = (pos - strand.last_pos) * strand.friction;
+        if pos.distance(connect_pos) > strand.dist + STRAND_SNAP_DISTANCE {
+            strand.last_pos = connect_pos;
+            transform.translation = connect_pos.extend(transform.translation.z);
+            continue;
+        }
 
+        let velocity = (pos - strand.last_pos) * strand.friction;
         strand.last_pos = pos;
 
-        let acceleration = Vec2::new(0.0, -strand.gravity);
-        pos += velocity + acceleration;
+        let wind: Vec2 = q_wind_zones
+            .iter()
+            .filter(|(zone, zone_transform)| {
+                let center = zone_transform.translation().truncate();
+                Rect::from_center_half_size(center, zone.half_extents).contains(pos)
+            })
+            .map(|(zone, _)| zone.force)
+            .sum();
+        let acceleration = Vec2::new(0.0, -strand.gravity) + wind;
 
-        if strand.physics {
-            let filter = QueryFilter::new().groups(CollisionGroups::new(
-                GroupLabel::STRAND,
-                GroupLabel::TERRAIN,
-            ));
-            if let Some((_, toi)) = rapier_context.cast_ray(
-                transform.translation.truncate(),
-                Vec2::new(0.0, -1.0),
-                2.0,
-                true,
-                filter,
-            ) {
-                let ground_level = transform.translation.y - toi;
-                if pos.y < ground_level {
-                    pos.y = ground_level;
-                }
-            }
-        }
+        let target = pos + velocity + acceleration;
+        pos = if strand.physics {
+            sweep_strand(&rapier_context, &ball, pos, target)
+        } else {
+            target
+        };
+
+        transform.translation = pos.extend(transform.translation.z);
+    }
+
+    for _ in 0..STRAND_SOLVER_ITERATIONS {
+        for (entity, strand) in strands.iter() {
+            let Some(connect_pos) = connect_position(
+                strand.connect,
+                &q_transforms,
+                &q_parents,
+                &q_global_transforms,
+            ) else {
+                continue;
+            };
+            let connect_pos = connect_pos + strand.offset;
+            let Ok(mut transform) = q_transforms.get_mut(*entity) else {
+                continue;
+            };
+            let pos = transform.translation.truncate();
 
-        let diff = connect_pos - pos;
-        if diff.length() != strand.dist {
+            let diff = connect_pos - pos;
             let dist_to_move = diff.length() - strand.dist;
-            pos += diff.normalize_or_zero() * dist_to_move;
-        }
+            if dist_to_move.abs() <= f32::EPSILON {
+                continue;
+            }
+            let target = pos + diff.normalize_or_zero() * dist_to_move * strand.stiffness;
+            let pos = if strand.physics {
+                sweep_strand(&rapier_context, &ball, pos, target)
+            } else {
+                target
+            };
 
-        transform.translation = pos.extend(transform.translation.z);
+            transform.translation = pos.extend(transform.translation.z);
+        }
     }
 }
 
-pub struct StrandLayerGroup<'a> {
-    assets: &'a [&'a str],
+/// The world position of the entity a strand is connected to. Strands have no parent, so their
+/// [`Transform`] is already in world space, but the entities they hang off of can be children of
+/// a level or world. Those are placed with their parent's [`GlobalTransform`], as their own is
+/// only updated after [`FixedUpdate`] has moved them.
+fn connect_position(
+    connect: Entity,
+    q_transforms: &Query<&mut Transform>,
+    q_parents: &Query<&Parent>,
+    q_global_transforms: &Query<&GlobalTransform>,
+) -> Option<Vec2> {
+    let translation = q_transforms.get(connect).ok()?.translation;
+    let world = match q_parents.get(connect) {
+        Ok(parent) => q_global_transforms
+            .get(parent.get())
+            .ok()?
+            .transform_point(translation),
+        Err(_) => translation,
+    };
+    Some(world.truncate())
 }
 
-impl<'a> StrandLayerGroup<'a> {
-    fn new(assets: &'a [&str]) -> Self {
-        StrandLayerGroup { assets }
+/// Moves a strand from `from` towards `to`, stopping at terrain. If the strand hits terrain, the
+/// rest of the movement is tried along each axis separately so the strand slides along it.
+fn sweep_strand(rapier_context: &RapierContext, ball: &Collider, from: Vec2, to: Vec2) -> Vec2 {
+    let pos = cast_strand(rapier_context, ball, from, to - from);
+    if pos == to {
+        return to;
     }
+    let remaining = to - pos;
+    let pos = cast_strand(rapier_context, ball, pos, Vec2::new(remaining.x, 0.0));
+    cast_strand(rapier_context, ball, pos, Vec2::new(0.0, remaining.y))
 }
 
+/// Casts the strand's circle from `from` along `delta`, returning how far it can move before it
+/// hits terrain.
+fn cast_strand(rapier_context: &RapierContext, ball: &Collider, from: Vec2, delta: Vec2) -> Vec2 {
+    let length = delta.length();
+    if length <= f32::EPSILON {
+        return from + delta;
+    }
+    let dir = delta / length;
+    let filter = QueryFilter::new().groups(CollisionGroups::new(
+        GroupLabel::STRAND,
+        GroupLabel::TERRAIN,
+    ));
+    match rapier_context.cast_shape(
+        from,
+        0.0,
+        dir,
+        ball,
+        ShapeCastOptions::with_max_time_of_impact(length),
+        filter,
+    ) {
+        Some((_, hit)) => from + dir * (hit.time_of_impact - STRAND_SKIN).max(0.0),
+        None => from + delta,
+    }
+}
+
+/// The path to the [`StrandPreset`](super::strand_preset::StrandPreset) with the player's hair and cloth.
+const PLAYER_STRAND_PRESET_PATH: &str = "strands/lyra.strands.toml";
+
+/// [`System`] that gives the player their hair and cloth from [`PLAYER_STRAND_PRESET_PATH`].
 pub fn add_player_hair_and_cloth(
     mut commands: Commands,
     q_player: Query<Entity, Added<PlayerMarker>>,
@@ -124,157 +281,130 @@ pub fn add_player_hair_and_cloth(
     let Ok(entity) = q_player.get_single() else {
         return;
     };
-    add_player_strand(
-        2.0,
-        0.2..0.15,
-        0.8,
-        &[
-            StrandLayerGroup::new(&["hair/clump_tiny_outline.png", "hair/clump_tiny.png"]),
-            StrandLayerGroup::new(&["hair/clump_small_outline.png", "hair/clump_small.png"]),
-            StrandLayerGroup::new(&["hair/clump_outline.png", "hair/clump.png"]),
-        ],
-        &[(2, false), (1, false), (1, false), (0, false)],
-        Vec3::new(-2.0, 3.0, -0.3),
-        PlayerRootStrandType::Hair,
-        &mut commands,
-        entity,
-        &asset_server,
-        Vec2::ZERO,
-    );
-    for i in 0..=1 {
-        add_player_strand(
-            1.0,
-            0.12..0.03,
-            0.6,
-            &[
-                StrandLayerGroup::new(&["cloth/clump_tiny_outline.png", "cloth/clump_tiny.png"]),
-                StrandLayerGroup::new(&["cloth/clump_small_outline.png", "cloth/clump_small.png"]),
-                StrandLayerGroup::new(&["cloth/clump_outline.png", "cloth/clump.png"]),
-            ],
-            &[
-                (1, false),
-                (1, false),
-                (0, false),
-                (0, false),
-                (0, false),
-                (0, true),
-                (0, true),
-                (0, true),
-                (0, true),
-                (0, true),
-                (0, true),
-            ],
-            // &[
-            //     1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            // ],
-            Vec3::new(if i == 0 { -3.0 } else { 5.0 }, -5.0, -0.2),
-            if i == 0 {
-                PlayerRootStrandType::LeftCloth
-            } else {
-                PlayerRootStrandType::RightCloth
-            },
-            &mut commands,
-            entity,
-            &asset_server,
-            Vec2::new(0.0, 1.0),
-        );
-    }
+    commands.entity(entity).insert(AttachedStrands::new(
+        asset_server.load(PLAYER_STRAND_PRESET_PATH),
+    ));
 }
 
-/// Creates a chain of strands to the player.
+/// Creates a chain of strands attached to `attach_to`, returning every entity spawned.
 ///
-/// Each created [`Strand`] component has a `dist` of `strand_dist`, and a `gravity` of at `strand_gravity.start` near the player that slowly turns into
-/// `strand_gravity.end`. The function layers the sprites in each list of `layer_groups`
-/// based on their order, and creates an entity for each index in `layer_group_order`.
-pub fn add_player_strand(
-    strand_dist: f32,
-    strand_gravity: Range<f32>,
-    strand_friction: f32,
-
-    layer_groups: &[StrandLayerGroup],
-    layer_group_order: &[(usize, bool)],
-    player_offset: Vec3,
-    player_root_strand_type: PlayerRootStrandType,
-
+/// Each created [`Strand`] component has a `dist` of `chain.dist`, a `stiffness` of `chain.stiffness` (except the root strand, which is
+/// pinned to `attach_to`), and a `gravity` of at `chain.gravity.start` near the root that slowly turns into
+/// `chain.gravity.end`. An entity is created for each of the chain's nodes, layering the sprites of the node in order.
+///
+/// Strands attached to the player match the player's pixel grid and z, and chains with a
+/// `player_root` have their root offset follow the player's pose through [`PlayerRootStrand`].
+pub fn spawn_strand_chain(
     commands: &mut Commands,
-    player_entity: Entity,
-    asset_server: &Res<AssetServer>,
-    sprite_translate: Vec2,
-) {
-    let mut connect = player_entity;
-    for (i, &(layer_index, physics)) in layer_group_order.iter().enumerate() {
+    chain: &StrandChain,
+    attach_to: Entity,
+    attach_z: f32,
+    on_player: bool,
+) -> Vec<Entity> {
+    let mut spawned = Vec::with_capacity(chain.nodes.len());
+    let mut connect = attach_to;
+    for (i, node) in chain.nodes.iter().enumerate() {
         let first = i == 0;
-        let strand_layer_group = &layer_groups[layer_index];
-        let new_id = commands
-            .spawn((
-                Strand::new(
-                    connect,
-                    if first {
-                        player_offset.truncate()
-                    } else {
-                        Vec2::ZERO
-                    },
-                    if first { 0.0 } else { strand_dist },
-                    strand_gravity.start
-                        + (i as f32 / layer_group_order.len() as f32)
-                            * (strand_gravity.end - strand_gravity.start),
-                    strand_friction,
-                    i as u32,
-                    physics,
-                ),
+        let mut strand = commands.spawn((
+            Strand::new(
+                connect,
+                if first { chain.offset } else { Vec2::ZERO },
+                if first { 0.0 } else { chain.dist },
+                chain.gravity.start
+                    + (i as f32 / chain.nodes.len() as f32)
+                        * (chain.gravity.end - chain.gravity.start),
+                chain.friction,
+                // the root strand is pinned to the entity it's attached to
+                if first { 1.0 } else { chain.stiffness },
+                i as u32,
+                node.physics,
+            ),
+            Transform::from_xyz(0.0, 0.0, attach_z + chain.z),
+            InheritedVisibility::default(),
+        ));
+        strand.with_children(|parent| {
+            for (layer_i, layer) in node.layers.iter().enumerate() {
+                let layer_transform = Transform::from_translation(
+                    Vec3::new(0., 0., (layer_i as f32) * 0.01) + chain.sprite_offset.extend(0.0),
+                );
+
+                parent.spawn((Sprite::from_image(layer.clone()), layer_transform));
+            }
+        });
+
+        if on_player {
+            strand.insert((
                 MatchPlayerPixel(default()),
-                Transform::default(),
-                InheritedVisibility::default(),
-                MatchPlayerZ {
-                    offset: player_offset.z,
-                },
-            ))
-            .with_children(|parent| {
-                for (layer_i, &layer) in strand_layer_group.assets.into_iter().enumerate() {
-                    let layer_transform = Transform::from_translation(
-                        Vec3::new(0., 0., (layer_i as f32) * 0.01) + sprite_translate.extend(0.0),
-                    );
-
-                    parent.spawn((
-                        Sprite::from_image(asset_server.load(layer)),
-                        layer_transform,
-                    ));
-                }
-            })
-            .id();
-        if first {
-            commands
-                .entity(new_id)
-                .insert(player_root_strand_type.clone());
+                MatchPlayerZ { offset: chain.z },
+            ));
+            if let (true, Some(ty)) = (first, chain.player_root) {
+                strand.insert(PlayerRootStrand {
+                    ty,
+                    base_offset: chain.offset,
+                });
+            }
         }
-        connect = new_id;
+
+        connect = strand.id();
+        spawned.push(connect);
     }
+    spawned
 }
 
-/// [`Component`] attached to the "root" strand (the strand closest to the player, the strand with `connect` equal to player)
-/// for all strand chains attached to player. Used to query [`Strand`] components to update`offset` in response to player model changing,
-/// e.g. lowering hair strand when player crouches.
-#[derive(Component, Debug, Clone)]
+/// The part of the player a chain of strands is attached to, set by `player_root` in a
+/// [`StrandPreset`](super::strand_preset::StrandPreset).
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlayerRootStrandType {
     Hair,
     LeftCloth,
     RightCloth,
 }
 
-/// [`System`] that updates [`Strand`] offsets based on [`PlayerRootStrandType`] and player state. Currently doesn't do anything,
-/// but should be used to make [`Strand`] offsets correct when player changes direction, crouches, etc.
+/// [`Component`] attached to the "root" strand (the strand closest to the player, the strand with `connect` equal to player)
+/// for all strand chains attached to player. Used to query [`Strand`] components to update`offset` in response to player model changing,
+/// e.g. lowering hair strand when player crouches.
+#[derive(Component, Debug, Clone)]
+pub struct PlayerRootStrand {
+    pub ty: PlayerRootStrandType,
+    /// The offset of the root strand from the player when the player is standing and facing right.
+    pub base_offset: Vec2,
+}
+
+impl PlayerRootStrand {
+    /// The offset of the root strand from the player, taking into account the direction the
+    /// player is facing and the pose of the player sprite.
+    fn offset(&self, facing: Facing, crouching: bool, state: &PlayerState) -> Vec2 {
+        let mut offset = self.base_offset;
+        let is_hair = self.ty == PlayerRootStrandType::Hair;
+
+        // the crouching sprite has its head lowered by a pixel and its waist raised, relative to
+        // the (lowered) center of the crouching player
+        if crouching {
+            offset.y += if is_hair { -1.0 } else { 2.0 };
+        }
+        match state {
+            // the player's body is stretched out while moving upwards
+            PlayerState::Jumping => offset.y += 1.0,
+            // cloth billows up while falling
+            PlayerState::Falling if !is_hair => offset.y += 1.0,
+            _ => {}
+        }
+
+        offset.x *= facing.sign();
+        offset
+    }
+}
+
+/// [`System`] that updates [`Strand`] offsets based on [`PlayerRootStrand`] and player state,
+/// mirroring them when the player faces left so that hair and cloth trail behind the player.
 pub fn update_player_strand_offsets(
-    mut strands: Query<(&mut Strand, &PlayerRootStrandType)>,
-    // currently queries for nothing, could be changed to query for a e.g. a Direction component.
-    player: Query<(), With<PlayerMarker>>,
+    mut strands: Query<(&mut Strand, &PlayerRootStrand)>,
+    player: Query<(&Facing, &PlayerMovement, &PlayerState), With<PlayerMarker>>,
 ) {
-    let Ok(()) = player.get_single() else { return }; // update this to read player state, e.g. player direction.
-    for (mut strand, ty) in strands.iter_mut() {
-        strand.offset = match ty {
-            // update these to dynamically reflect player state, e.g. setting the Hair strand's offset to (2.0, 3.0) when facing left.
-            PlayerRootStrandType::Hair => Vec2::new(-2.0, 3.0),
-            PlayerRootStrandType::LeftCloth => Vec2::new(-3.0, -5.0),
-            PlayerRootStrandType::RightCloth => Vec2::new(5.0, -5.0),
-        };
+    let Ok((facing, movement, state)) = player.get_single() else {
+        return;
+    };
+    for (mut strand, root) in strands.iter_mut() {
+        strand.offset = root.offset(*facing, movement.crouching, state);
     }
 }
@@ -0,0 +1,291 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use bevy_ecs_ldtk::prelude::*;
+use serde::Deserialize;
+
+use crate::level::{error::LevelDataCheck, LevelSystems};
+
+use super::{
+    strand::{spawn_strand_chain, PlayerRootStrandType},
+    PlayerMarker,
+};
+
+/// [`Plugin`] that loads [`StrandPreset`]s and spawns the strands of every entity with
+/// [`AttachedStrands`], respawning them whenever their preset is hot reloaded. Ldtk entities get
+/// [`AttachedStrands`] from their `strand_preset` field, such as the "Strands" entity used for
+/// banners and vines.
+pub struct StrandPresetPlugin;
+
+impl Plugin for StrandPresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<StrandPreset>()
+            .init_asset_loader::<StrandPresetLoader>()
+            .register_ldtk_entity::<StrandsBundle>("Strands")
+            .add_systems(
+                PreUpdate,
+                attach_ldtk_strand_presets.in_set(LevelSystems::Processing),
+            )
+            .add_systems(Update, spawn_attached_strands)
+            .add_observer(on_remove_attached_strands_despawn_strands);
+    }
+}
+
+/// The format of a chain in a [`StrandPreset`] on disk.
+#[derive(Deserialize)]
+struct StrandChainFile {
+    /// Set on chains attached to the player, so that the chain follows the player's pose
+    player_root: Option<PlayerRootStrandType>,
+    /// Offset of the root of the chain from the entity it's attached to, when facing right
+    offset: [f32; 2],
+    /// Z offset of the chain from the entity it's attached to
+    #[serde(default)]
+    z: f32,
+    dist: f32,
+    /// Gravity of the strand nearest to the root, and of the strand at the end of the chain
+    gravity: [f32; 2],
+    friction: f32,
+    #[serde(default = "default_stiffness")]
+    stiffness: f32,
+    /// Offset of every sprite in the chain from its strand
+    #[serde(default)]
+    sprite_offset: [f32; 2],
+    /// Groups of sprites, relative to the assets folder. Each group is layered in order.
+    layers: Vec<Vec<String>>,
+    nodes: Vec<StrandNodeFile>,
+}
+
+fn default_stiffness() -> f32 {
+    1.0
+}
+
+/// The format of a node in a [`StrandChain`] on disk, one for each strand in the chain.
+#[derive(Deserialize)]
+struct StrandNodeFile {
+    /// Index into the chain's `layers`
+    layer: usize,
+    #[serde(default)]
+    physics: bool,
+}
+
+/// The format of a [`StrandPreset`] on disk (`*.strands.toml`).
+#[derive(Deserialize)]
+struct StrandPresetFile {
+    chains: Vec<StrandChainFile>,
+}
+
+/// A single strand in a [`StrandChain`].
+#[derive(Debug, Clone)]
+pub struct StrandNode {
+    /// Sprites of the strand, layered in order
+    pub layers: Vec<Handle<Image>>,
+    /// Whether the strand collides with terrain
+    pub physics: bool,
+}
+
+/// A chain of [`Strand`](super::strand::Strand)s hanging off an entity, e.g. a clump of hair or
+/// the corner of a cape.
+#[derive(Debug, Clone)]
+pub struct StrandChain {
+    pub player_root: Option<PlayerRootStrandType>,
+    pub offset: Vec2,
+    pub z: f32,
+    pub dist: f32,
+    pub gravity: Range<f32>,
+    pub friction: f32,
+    pub stiffness: f32,
+    pub sprite_offset: Vec2,
+    pub nodes: Vec<StrandNode>,
+}
+
+/// [`Asset`] describing the strand chains of a costume, banner, vine, etc. Loaded from
+/// `*.strands.toml` files so that new strands can be made without changing any code.
+#[derive(Asset, TypePath, Debug)]
+pub struct StrandPreset {
+    pub chains: Vec<StrandChain>,
+}
+
+#[derive(Default)]
+struct StrandPresetLoader;
+
+impl AssetLoader for StrandPresetLoader {
+    type Asset = StrandPreset;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: StrandPresetFile = toml::from_str(std::str::from_utf8(&bytes)?)?;
+
+        let mut chains = Vec::with_capacity(file.chains.len());
+        for (chain_i, chain) in file.chains.into_iter().enumerate() {
+            if chain.nodes.is_empty() {
+                return Err(format!("Chain {chain_i} needs at least one node").into());
+            }
+
+            // load each sprite once, even if it is used by many nodes
+            let layers: Vec<Vec<Handle<Image>>> = chain
+                .layers
+                .into_iter()
+                .map(|group| {
+                    group
+                        .into_iter()
+                        .map(|path| load_context.load(path))
+                        .collect()
+                })
+                .collect();
+
+            let mut nodes = Vec::with_capacity(chain.nodes.len());
+            for node in chain.nodes {
+                let Some(node_layers) = layers.get(node.layer) else {
+                    return Err(format!(
+                        "Chain {chain_i} has a node with layer {}, but only has {} layers",
+                        node.layer,
+                        layers.len()
+                    )
+                    .into());
+                };
+                nodes.push(StrandNode {
+                    layers: node_layers.clone(),
+                    physics: node.physics,
+                });
+            }
+
+            chains.push(StrandChain {
+                player_root: chain.player_root,
+                offset: Vec2::from(chain.offset),
+                z: chain.z,
+                dist: chain.dist,
+                gravity: chain.gravity[0]..chain.gravity[1],
+                friction: chain.friction,
+                stiffness: chain.stiffness,
+                sprite_offset: Vec2::from(chain.sprite_offset),
+                nodes,
+            });
+        }
+
+        Ok(StrandPreset { chains })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["strands.toml"]
+    }
+}
+
+/// [`Component`] that attaches the strands described by a [`StrandPreset`] to an entity. The
+/// strands are spawned once the preset has loaded, and are respawned whenever it changes.
+#[derive(Component)]
+pub struct AttachedStrands {
+    pub preset: Handle<StrandPreset>,
+    spawned: Vec<Entity>,
+    dirty: bool,
+}
+
+impl AttachedStrands {
+    pub fn new(preset: Handle<StrandPreset>) -> Self {
+        AttachedStrands {
+            preset,
+            spawned: Vec::new(),
+            dirty: true,
+        }
+    }
+}
+
+/// [`Bundle`] registered with Ldtk for entities that only hang strands, e.g. banners and vines.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct StrandsBundle {
+    #[from_entity_instance]
+    check: LevelDataCheck,
+}
+
+/// [`System`] that gives every Ldtk entity with a `strand_preset` field the [`AttachedStrands`]
+/// of that preset.
+fn attach_ldtk_strand_presets(
+    mut commands: Commands,
+    q_entities: Query<(Entity, &EntityInstance), Added<EntityInstance>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, entity_instance) in q_entities.iter() {
+        let Ok(Some(path)) = entity_instance.get_maybe_string_field("strand_preset") else {
+            continue;
+        };
+        if path.is_empty() {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .insert(AttachedStrands::new(asset_server.load(path.clone())));
+    }
+}
+
+/// [`System`] that spawns the strands of [`AttachedStrands`] entities, replacing them with new
+/// strands when their [`StrandPreset`] is hot reloaded.
+fn spawn_attached_strands(
+    mut commands: Commands,
+    mut q_attached: Query<(Entity, &mut AttachedStrands, &Transform, Has<PlayerMarker>)>,
+    mut ev_preset: EventReader<AssetEvent<StrandPreset>>,
+    presets: Res<Assets<StrandPreset>>,
+) {
+    for event in ev_preset.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        for (_, mut attached, _, _) in q_attached.iter_mut() {
+            if attached.preset.id() == *id {
+                attached.dirty = true;
+            }
+        }
+    }
+
+    for (entity, mut attached, transform, is_player) in q_attached.iter_mut() {
+        if !attached.dirty {
+            continue;
+        }
+        let Some(preset) = presets.get(&attached.preset) else {
+            continue;
+        };
+
+        for strand in attached.spawned.drain(..) {
+            commands.entity(strand).despawn_recursive();
+        }
+        let spawned = preset
+            .chains
+            .iter()
+            .flat_map(|chain| {
+                spawn_strand_chain(
+                    &mut commands,
+                    chain,
+                    entity,
+                    transform.translation.z,
+                    is_player,
+                )
+            })
+            .collect();
+        attached.spawned = spawned;
+        attached.dirty = false;
+    }
+}
+
+fn on_remove_attached_strands_despawn_strands(
+    removed: Trigger<OnRemove, AttachedStrands>,
+    mut commands: Commands,
+    q_attached: Query<&AttachedStrands>,
+) {
+    let Ok(attached) = q_attached.get(removed.entity()) else {
+        return;
+    };
+    for &strand in attached.spawned.iter() {
+        if let Some(mut strand) = commands.get_entity(strand) {
+            strand.despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,532 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Difficulty},
+    level::{
+        checkpoint::{ActiveCheckpoint, ActiveCheckpoints},
+        level_world_box,
+        misc::start_flag_position,
+        playlist::Playlist,
+        shard::CollectedShards,
+        CompletedLevels, CurrentLevel, LevelSystems, PendingTeleport,
+    },
+    player::PlayerMarker,
+    shared::ResetLevel,
+};
+
+/// The version of the save format written by this build. Bump it whenever [`SaveData`] changes in
+/// a way that old saves can't be read as, and add a migration to [`MIGRATIONS`].
+pub const SAVE_VERSION: u32 = 1;
+
+/// Upgrades a save from one version to the next by editing its TOML in place.
+type Migration = fn(&mut toml::Table);
+
+/// Migrations from each old save version to the next, the first one upgrading version 1 saves.
+const MIGRATIONS: [Migration; SAVE_VERSION as usize - 1] = [];
+
+/// [`Plugin`] that saves the player's progress to disk, and puts them back where they left off
+/// when the game starts, resuming the [`Playlist`] at the project the save was made in. Saves from
+/// a project that is no longer in the playlist are ignored, and saves that can't be loaded are
+/// moved to `save.toml.bak` so that they aren't overwritten.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        let enabled = app.world().resource::<Config>().save_config.enabled;
+        let mut path = if enabled { save_path() } else { None };
+        let mut save = None;
+        if let Some(save_path) = path.clone() {
+            match load_save(&save_path) {
+                Ok(loaded) => save = Some(loaded),
+                Err(SaveError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    error!("Failed to load save {}: {err}", save_path.display());
+                    path = back_up_save(&save_path);
+                }
+            }
+        }
+
+        if let Some(save) = &save {
+            // the difficulty can only be changed in the config, so a save made with another one
+            // is carried over to the config's difficulty
+            let difficulty = app.world().resource::<Config>().health_config.difficulty;
+            if save.settings.difficulty != difficulty {
+                info!(
+                    "Save was made on {:?} difficulty, continuing on {difficulty:?} from the config",
+                    save.settings.difficulty
+                );
+            }
+
+            // fall back to looking for the project in case the playlist was reordered
+            let mut playlist = app.world_mut().resource_mut::<Playlist>();
+            let index = Some(save.playlist_index)
+                .filter(|&index| {
+                    playlist
+                        .entries()
+                        .get(index)
+                        .is_some_and(|entry| entry.path == save.project)
+                })
+                .or_else(|| {
+                    playlist
+                        .entries()
+                        .iter()
+                        .position(|entry| entry.path == save.project)
+                });
+            if let Some(index) = index {
+                playlist.resume_at(index);
+            }
+        }
+
+        app.insert_resource(SaveFile {
+            path,
+            pending: save,
+            written: None,
+        })
+        .add_systems(
+            Update,
+            (restore_progress, write_save)
+                .chain()
+                .in_set(LevelSystems::Simulation),
+        );
+    }
+}
+
+/// Everything that is saved to disk. Level and entity iids are stored as plain strings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaveData {
+    pub version: u32,
+    /// The path of the Ldtk project the save was made in, relative to the assets folder
+    pub project: String,
+    /// The index of the [`Playlist`] entry the save was made in
+    pub playlist_index: usize,
+    /// The level the player was last in
+    pub last_level: Option<String>,
+    #[serde(default)]
+    pub completed_levels: BTreeSet<String>,
+    /// The entity iids of the shards the player has collected and kept
+    #[serde(default)]
+    pub collectibles: BTreeSet<String>,
+    /// The active checkpoint of the last level, if one was activated
+    pub checkpoint: Option<SavedCheckpoint>,
+    #[serde(default)]
+    pub settings: SavedSettings,
+}
+
+impl SaveData {
+    pub fn new(project: String, playlist_index: usize) -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            project,
+            playlist_index,
+            last_level: None,
+            completed_levels: BTreeSet::new(),
+            collectibles: BTreeSet::new(),
+            checkpoint: None,
+            settings: SavedSettings::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedCheckpoint {
+    pub level_iid: String,
+    pub entity_iid: String,
+    pub respawn_point: [f32; 2],
+}
+
+/// Settings the save was made with. The [`Config`] wins over them when the two differ.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SavedSettings {
+    pub difficulty: Difficulty,
+}
+
+/// What went wrong reading or writing a save.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// The save has no `version`, so there is no way to know how to read it
+    MissingVersion,
+    /// The save was written by a newer build, or has a version that never existed
+    UnsupportedVersion(i64),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{err}"),
+            SaveError::Parse(err) => write!(f, "{err}"),
+            SaveError::Serialize(err) => write!(f, "{err}"),
+            SaveError::MissingVersion => write!(f, "missing the save version"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported save version {version}, the latest is {SAVE_VERSION}"
+                )
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SaveError {
+    fn from(err: toml::de::Error) -> Self {
+        SaveError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(err: toml::ser::Error) -> Self {
+        SaveError::Serialize(err)
+    }
+}
+
+/// Reads a save, migrating it to the current [`SAVE_VERSION`] if it is older.
+pub fn parse_save(contents: &str) -> Result<SaveData, SaveError> {
+    parse_save_with(contents, &MIGRATIONS)
+}
+
+fn parse_save_with(contents: &str, migrations: &[Migration]) -> Result<SaveData, SaveError> {
+    let mut table: toml::Table = contents.parse()?;
+    let version = table
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .ok_or(SaveError::MissingVersion)?;
+    let latest = migrations.len() as i64 + 1;
+    if !(1..=latest).contains(&version) {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    for migration in migrations[version as usize - 1..].iter() {
+        migration(&mut table);
+    }
+    table.insert("version".to_string(), toml::Value::Integer(latest));
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+pub fn write_save_to_string(save: &SaveData) -> Result<String, SaveError> {
+    Ok(toml::to_string(save)?)
+}
+
+fn load_save(path: &Path) -> Result<SaveData, SaveError> {
+    parse_save(&std::fs::read_to_string(path)?)
+}
+
+/// Writes the save to a temporary file next to it, then renames it over the save, so that the
+/// game closing mid-write can't leave a half written save behind.
+fn store_save(path: &Path, save: &SaveData) -> Result<(), SaveError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension("toml.tmp");
+    std::fs::write(&temp_path, write_save_to_string(save)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Moves a save that couldn't be loaded to `save.toml.bak`, so that starting over doesn't
+/// overwrite it. Returns where to keep saving, which is nowhere if the save couldn't be moved.
+fn back_up_save(path: &Path) -> Option<PathBuf> {
+    let backup_path = path.with_extension("toml.bak");
+    match std::fs::rename(path, &backup_path) {
+        Ok(()) => {
+            warn!("Moved the save to {}", backup_path.display());
+            Some(path.to_path_buf())
+        }
+        Err(err) => {
+            error!(
+                "Failed to move the save to {}, saving is turned off: {err}",
+                backup_path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Where the save is kept, in the user's data directory. There is none on the web.
+fn save_path() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "windows") {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(std::env::var_os("HOME")?).join("Library/Application Support")
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".local/share")))?
+    };
+    Some(data_dir.join("lightborne").join("save.toml"))
+}
+
+/// [`Resource`] for the save file on disk.
+#[derive(Resource)]
+pub struct SaveFile {
+    /// `None` if saving is turned off or there is nowhere to save
+    path: Option<PathBuf>,
+    /// The save loaded at startup, until the player has been put back where they left off
+    pending: Option<SaveData>,
+    /// What was last written to disk, so that unchanged progress isn't written again
+    written: Option<SaveData>,
+}
+
+/// [`System`] that restores the progress in the loaded save once the project has loaded and the
+/// player has spawned, moving the player to their checkpoint or the start flag of their last
+/// level.
+#[allow(clippy::too_many_arguments)]
+fn restore_progress(
+    mut save_file: ResMut<SaveFile>,
+    playlist: Res<Playlist>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut q_player: Query<&mut Transform, With<PlayerMarker>>,
+    mut completed_levels: ResMut<CompletedLevels>,
+    mut active_checkpoints: ResMut<ActiveCheckpoints>,
+    mut collected_shards: ResMut<CollectedShards>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut ev_reset_level: EventWriter<ResetLevel>,
+) {
+    let Some(save) = &save_file.pending else {
+        return;
+    };
+    if save.project != playlist.current().path {
+        info!(
+            "Ignoring save made in {}, which is not in the playlist",
+            save.project
+        );
+        save_file.pending = None;
+        return;
+    }
+    let Some(project) = ldtk_project_assets.get(&playlist.current_project()) else {
+        return;
+    };
+    let Ok(mut transform) = q_player.get_single_mut() else {
+        return;
+    };
+    let save = save_file.pending.take().expect("Save should be pending");
+
+    completed_levels.0 = save
+        .completed_levels
+        .iter()
+        .map(|iid| LevelIid::new(iid.clone()))
+        .collect();
+    // shards are tracked by level, so find the level each saved shard is in
+    for level in project.iter_raw_levels() {
+        let shards = level
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| layer.entity_instances.iter())
+            .filter(|entity| save.collectibles.contains(&entity.iid));
+        for shard in shards {
+            collected_shards.insert(
+                LevelIid::new(level.iid.clone()),
+                EntityIid::new(shard.iid.clone()),
+            );
+        }
+    }
+    if let Some(checkpoint) = &save.checkpoint {
+        active_checkpoints.insert(
+            LevelIid::new(checkpoint.level_iid.clone()),
+            ActiveCheckpoint {
+                iid: EntityIid::new(checkpoint.entity_iid.clone()),
+                respawn_point: checkpoint.respawn_point.into(),
+                crystals: None,
+                world_crystal_links: None,
+            },
+        );
+    }
+
+    let Some(level) = save
+        .last_level
+        .as_ref()
+        .and_then(|iid| project.get_raw_level_by_iid(iid))
+    else {
+        return;
+    };
+    let start = match &save.checkpoint {
+        Some(checkpoint) if checkpoint.level_iid == level.iid => {
+            Some(checkpoint.respawn_point.into())
+        }
+        _ => start_flag_position(level),
+    };
+    let Some(start) = start else {
+        warn!(
+            "{} has no start flag to move the player to",
+            level.identifier
+        );
+        return;
+    };
+
+    *current_level = CurrentLevel {
+        level_iid: LevelIid::new(level.iid.clone()),
+        level_entity: None,
+        world_box: level_world_box(level),
+    };
+    *level_selection = LevelSelection::iid(level.iid.clone());
+    transform.translation.x = start.x;
+    transform.translation.y = start.y;
+    pending_teleport.0 = Some(start);
+    ev_reset_level.send(ResetLevel::Switching);
+}
+
+/// [`System`] that writes the player's progress to disk whenever it changes.
+#[allow(clippy::too_many_arguments)]
+fn write_save(
+    mut save_file: ResMut<SaveFile>,
+    config: Res<Config>,
+    playlist: Res<Playlist>,
+    current_level: Res<CurrentLevel>,
+    completed_levels: Res<CompletedLevels>,
+    active_checkpoints: Res<ActiveCheckpoints>,
+    collected_shards: Res<CollectedShards>,
+) {
+    if save_file.pending.is_some() {
+        return;
+    }
+    let Some(path) = save_file.path.clone() else {
+        return;
+    };
+    if current_level.level_iid.get().is_empty()
+        || !(current_level.is_changed()
+            || completed_levels.is_changed()
+            || active_checkpoints.is_changed()
+            || collected_shards.is_changed())
+    {
+        return;
+    }
+
+    let save = SaveData {
+        last_level: Some(current_level.level_iid.get().clone()),
+        completed_levels: completed_levels
+            .0
+            .iter()
+            .map(|iid| iid.get().clone())
+            .collect(),
+        collectibles: collected_shards
+            .kept()
+            .map(|iid| iid.get().clone())
+            .collect(),
+        checkpoint: active_checkpoints
+            .get(&current_level.level_iid)
+            .map(|checkpoint| SavedCheckpoint {
+                level_iid: current_level.level_iid.get().clone(),
+                entity_iid: checkpoint.iid.get().clone(),
+                respawn_point: checkpoint.respawn_point.into(),
+            }),
+        settings: SavedSettings {
+            difficulty: config.health_config.difficulty,
+        },
+        ..SaveData::new(playlist.current().path.clone(), playlist.current_index())
+    };
+    if save_file.written.as_ref() == Some(&save) {
+        return;
+    }
+    if let Err(err) = store_save(&path, &save) {
+        error!("Failed to write save {}: {err}", path.display());
+    }
+    save_file.written = Some(save);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_save() -> SaveData {
+        SaveData {
+            last_level: Some("level-b".to_string()),
+            completed_levels: ["level-a".to_string(), "level-c".to_string()].into(),
+            collectibles: ["shard-1".to_string()].into(),
+            checkpoint: Some(SavedCheckpoint {
+                level_iid: "level-b".to_string(),
+                entity_iid: "checkpoint-1".to_string(),
+                respawn_point: [120.0, -48.5],
+            }),
+            settings: SavedSettings {
+                difficulty: Difficulty::Health,
+            },
+            ..SaveData::new("levels/lightborne.ldtk".to_string(), 2)
+        }
+    }
+
+    #[test]
+    fn round_trips_empty_save() {
+        let save = SaveData::new("levels/lightborne.ldtk".to_string(), 0);
+        let contents = write_save_to_string(&save).unwrap();
+        assert_eq!(parse_save(&contents).unwrap(), save);
+    }
+
+    #[test]
+    fn round_trips_full_save() {
+        let save = full_save();
+        let contents = write_save_to_string(&save).unwrap();
+        assert_eq!(parse_save(&contents).unwrap(), save);
+    }
+
+    #[test]
+    fn written_save_has_current_version() {
+        let contents = write_save_to_string(&full_save()).unwrap();
+        let table: toml::Table = contents.parse().unwrap();
+        assert_eq!(
+            table.get("version").and_then(toml::Value::as_integer),
+            Some(SAVE_VERSION as i64)
+        );
+    }
+
+    #[test]
+    fn rejects_save_without_version() {
+        let result = parse_save("project = \"levels/lightborne.ldtk\"");
+        assert!(matches!(result, Err(SaveError::MissingVersion)));
+    }
+
+    #[test]
+    fn rejects_newer_save() {
+        let contents = format!(
+            "version = {}\nproject = \"levels/lightborne.ldtk\"",
+            SAVE_VERSION + 1
+        );
+        let result = parse_save(&contents);
+        assert!(matches!(result, Err(SaveError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn migrates_old_saves_in_order() {
+        // pretend version 1 saves called the project `world`, and version 2 saves had no
+        // completed levels
+        fn rename_world(table: &mut toml::Table) {
+            let world = table.remove("world").unwrap();
+            table.insert("project".to_string(), world);
+        }
+        fn complete_first_level(table: &mut toml::Table) {
+            table.insert(
+                "completed_levels".to_string(),
+                toml::Value::Array(vec!["level-a".into()]),
+            );
+        }
+        let migrations: [Migration; 2] = [rename_world, complete_first_level];
+
+        let v1 = "version = 1\nworld = \"levels/lightborne.ldtk\"\nplaylist_index = 0";
+        let save = parse_save_with(v1, &migrations).unwrap();
+        assert_eq!(save.version, 3);
+        assert_eq!(save.project, "levels/lightborne.ldtk");
+        assert!(save.completed_levels.contains("level-a"));
+
+        let v2 = "version = 2\nproject = \"levels/lightborne.ldtk\"\nplaylist_index = 0";
+        let save = parse_save_with(v2, &migrations).unwrap();
+        assert_eq!(save.project, "levels/lightborne.ldtk");
+        assert!(save.completed_levels.contains("level-a"));
+    }
+}
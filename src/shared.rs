@@ -14,6 +14,8 @@ impl GroupLabel {
     pub const WHITE_RAY: Group = Group::GROUP_7;
     pub const STRAND: Group = Group::GROUP_8;
     pub const BLUE_RAY: Group = Group::GROUP_9;
+    /// Sensors that do something when the player walks into them, e.g. checkpoints
+    pub const TRIGGER: Group = Group::GROUP_10;
     pub const ALL: Group = Group::from_bits_truncate(!0);
 }
 
@@ -21,6 +23,27 @@ impl GroupLabel {
 pub enum GameState {
     Playing,
     Paused,
+    /// The player has died and the death sequence is playing, see [`RespawnState`].
+    Respawning,
+    /// The level select menu is open, see
+    /// [`LevelSelectPlugin`](crate::level_select::LevelSelectPlugin).
+    LevelSelect,
+    /// A level has been completed and its summary is shown, see
+    /// [`LevelSummaryPlugin`](crate::level_summary::LevelSummaryPlugin).
+    LevelSummary,
+}
+
+/// Sub-state of [`GameState::Respawning`], tracking each stage of the death sequence in order.
+#[derive(SubStates, Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[source(GameState = GameState::Respawning)]
+pub enum RespawnState {
+    /// The player bursts into particles
+    #[default]
+    Dying,
+    /// The screen fades to black
+    FadeOut,
+    /// The level has been reset and the screen fades back in
+    FadeIn,
 }
 
 #[derive(Event, PartialEq, Eq)]